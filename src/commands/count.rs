@@ -1,5 +1,7 @@
 use crate::stats::counter::ProjectStats;
-use crate::types::OutputFormat;
+use crate::types::{
+    ExtensionMapping, FallbackEncoding, FileSort, IgnorePolicy, MixedPolicy, OutputFormat,
+};
 use crate::utils::output::OutputFormatter;
 use std::path::PathBuf;
 use std::time::Instant;
@@ -8,18 +10,56 @@ pub struct CountConfig {
     pub path: PathBuf,
     pub include_hidden: bool,
     pub output_format: OutputFormat,
-    pub respect_gitignore: bool,
+    /// Which ignore files the walk applies.
+    pub ignore: IgnorePolicy,
     pub include_blank_lines: bool,
     pub include_comments: bool,
+    pub mixed_as: MixedPolicy,
+    pub fallback_encoding: FallbackEncoding,
+    /// Number of walker threads; 0 lets the walker choose.
+    pub threads: usize,
+    /// Extra language definitions that extend or override the built-ins.
+    pub languages: Option<PathBuf>,
+    /// Extension overrides from `--map`; later entries win.
+    pub mappings: Vec<ExtensionMapping>,
+    /// Explain detection decisions on stderr.
+    pub verbose: bool,
+    /// Keep and report counts for every file.
+    pub by_file: bool,
+    /// Order of the per-file report.
+    pub sort: FileSort,
+    /// Roll counts up per directory.
+    pub by_dir: bool,
+    /// How many directory levels below the root `by_dir` reports.
+    pub depth: usize,
+    /// Stop at the first unreadable path instead of skipping it.
+    pub strict: bool,
+    /// Walk into symlinked files and directories.
+    pub follow_symlinks: bool,
+    /// Only scan paths matching one of these globs.
+    pub include: Vec<String>,
+    /// Skip paths matching any of these globs.
+    pub exclude: Vec<String>,
+    /// Only count files in these languages.
+    pub only_languages: Vec<String>,
+    /// Leave out files in these languages.
+    pub excluded_languages: Vec<String>,
+    /// Leave out generated and minified files.
+    pub exclude_generated: bool,
+    /// Paths of third-party code, on top of `vendor/`, `third_party/` and the like.
+    pub vendored_patterns: Vec<String>,
 }
 
-pub fn run(config: CountConfig) -> Result<(), Box<dyn std::error::Error>> {
+/// Scan, display and return the statistics. Paths that could not be read
+/// are summarised on stderr; the caller decides what they mean for the exit code.
+pub fn run(config: CountConfig) -> Result<ProjectStats, Box<dyn std::error::Error>> {
     println!("🦅 Tallyhawk surveying: {}", config.path.display());
 
     let start_time = Instant::now();
 
     let mut stats = ProjectStats::new();
     stats.scan_directory(&config.path, &config)?;
+    stats.sort_files(config.sort);
 
     let duration = start_time.elapsed();
 
@@ -28,5 +68,22 @@ pub fn run(config: CountConfig) -> Result<(), Box<dyn std::error::Error>> {
 
     println!("\n⚡ Analysis completed in {}ms", duration.as_millis());
 
-    Ok(())
+    if !stats.errors.is_empty() {
+        let noun = if stats.errors.len() == 1 {
+            "path"
+        } else {
+            "paths"
+        };
+        eprintln!("\n⚠️  {} {} could not be read:", stats.errors.len(), noun);
+        for error in &stats.errors {
+            eprintln!(
+                "  {}: {} ({})",
+                error.path.as_deref().unwrap_or("<unknown>"),
+                error.message,
+                error.kind
+            );
+        }
+    }
+
+    Ok(stats)
 }
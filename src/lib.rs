@@ -11,7 +11,10 @@ pub mod stats;
 pub mod types;
 pub mod utils;
 
-pub use types::OutputFormat;
+pub use types::{
+    ExtensionMapping, FallbackEncoding, FileSort, IgnorePolicy, MappingTarget, MixedPolicy,
+    OutputFormat,
+};
 
 pub use commands::count::{run as count, CountConfig};
 pub use stats::counter::ProjectStats;
@@ -2,11 +2,15 @@ use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
 mod commands;
+// Some lookups are only used through the library API and its tests
+#[allow(dead_code)]
 mod stats;
 mod types;
 mod utils;
 
-use types::OutputFormat;
+use types::{
+    ExtensionMapping, FallbackEncoding, FileSort, IgnorePolicy, MixedPolicy, OutputFormat,
+};
 
 #[derive(Parser)]
 #[command(name = "tallyhawk")]
@@ -32,9 +36,29 @@ enum Commands {
         #[arg(short, long, value_enum, default_value = "table")]
         format: OutputFormat,
 
-        /// Respect .gitignore files
-        #[arg(long, default_value = "true")]
-        gitignore: bool,
+        /// Do not apply .gitignore files or .git/info/exclude
+        #[arg(long)]
+        no_gitignore: bool,
+
+        /// Do not apply the global gitignore (core.excludesFile)
+        #[arg(long)]
+        no_global_gitignore: bool,
+
+        /// Do not apply .ignore files
+        #[arg(long)]
+        no_ignore_files: bool,
+
+        /// Do not apply .tallyhawkignore files
+        #[arg(long)]
+        no_tallyhawkignore: bool,
+
+        /// Do not apply ignore files in directories above the scanned path
+        #[arg(long)]
+        no_ignore_parent: bool,
+
+        /// Do not apply any ignore files
+        #[arg(long)]
+        no_ignore: bool,
 
         /// Include blank lines in count
         #[arg(long)]
@@ -43,6 +67,78 @@ enum Commands {
         /// Include comments in count
         #[arg(long)]
         include_comments: bool,
+
+        /// Count lines with code and a trailing comment as code, comment or both
+        #[arg(long, value_enum, default_value = "code")]
+        mixed_as: MixedPolicy,
+
+        /// Codepage for files that are neither UTF-8 nor UTF-16
+        #[arg(long, value_enum, default_value = "latin1")]
+        fallback_encoding: FallbackEncoding,
+
+        /// Number of threads to scan with (0 = automatic)
+        #[arg(short = 'j', long, default_value_t = 0)]
+        threads: usize,
+
+        /// TOML file with extra language definitions
+        #[arg(long, value_name = "FILE")]
+        languages: Option<PathBuf>,
+
+        /// Map an extension to a language, `binary` or `ignore` (repeatable)
+        #[arg(long = "map", value_name = "EXT=LANG")]
+        map: Vec<ExtensionMapping>,
+
+        /// Explain how ambiguous files were classified
+        #[arg(short, long)]
+        verbose: bool,
+
+        /// List every file with its own counts
+        #[arg(long)]
+        by_file: bool,
+
+        /// Order of the per-file list
+        #[arg(long, value_enum, default_value = "lines")]
+        sort: FileSort,
+
+        /// Roll counts up per directory
+        #[arg(long)]
+        by_dir: bool,
+
+        /// Directory levels shown by --by-dir
+        #[arg(long, default_value_t = 1, requires = "by_dir")]
+        depth: usize,
+
+        /// Stop at the first unreadable file instead of skipping it
+        #[arg(long)]
+        strict: bool,
+
+        /// Follow symbolic links (links back to a parent directory are skipped)
+        #[arg(short = 'L', long)]
+        follow_symlinks: bool,
+
+        /// Only scan paths matching this glob (repeatable)
+        #[arg(long, value_name = "GLOB")]
+        include: Vec<String>,
+
+        /// Skip paths matching this glob (repeatable)
+        #[arg(long, value_name = "GLOB")]
+        exclude: Vec<String>,
+
+        /// Only count these languages (comma-separated)
+        #[arg(long, value_name = "LANGS", value_delimiter = ',')]
+        lang: Vec<String>,
+
+        /// Leave out these languages (comma-separated)
+        #[arg(long, value_name = "LANGS", value_delimiter = ',')]
+        exclude_lang: Vec<String>,
+
+        /// Leave out generated and minified files
+        #[arg(long)]
+        exclude_generated: bool,
+
+        /// Treat paths matching this glob as vendored third-party code (repeatable)
+        #[arg(long = "vendored", value_name = "GLOB")]
+        vendored: Vec<String>,
     },
 }
 
@@ -54,20 +150,77 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             path,
             all,
             format,
-            gitignore,
+            no_gitignore,
+            no_global_gitignore,
+            no_ignore_files,
+            no_tallyhawkignore,
+            no_ignore_parent,
+            no_ignore,
             include_blanks,
             include_comments,
+            mixed_as,
+            fallback_encoding,
+            threads,
+            languages,
+            map,
+            verbose,
+            by_file,
+            sort,
+            by_dir,
+            depth,
+            strict,
+            follow_symlinks,
+            include,
+            exclude,
+            lang,
+            exclude_lang,
+            exclude_generated,
+            vendored,
         } => {
+            let ignore = if no_ignore {
+                IgnorePolicy::none()
+            } else {
+                IgnorePolicy {
+                    gitignore: !no_gitignore,
+                    global_gitignore: !no_global_gitignore,
+                    ignore_files: !no_ignore_files,
+                    tallyhawkignore: !no_tallyhawkignore,
+                    parents: !no_ignore_parent,
+                }
+            };
+
             let config = commands::count::CountConfig {
                 path,
                 include_hidden: all,
                 output_format: format,
-                respect_gitignore: gitignore,
+                ignore,
                 include_blank_lines: include_blanks,
                 include_comments,
+                mixed_as,
+                fallback_encoding,
+                threads,
+                languages,
+                mappings: map,
+                verbose,
+                by_file,
+                sort,
+                by_dir,
+                depth,
+                strict,
+                follow_symlinks,
+                include,
+                exclude,
+                only_languages: lang,
+                excluded_languages: exclude_lang,
+                exclude_generated,
+                vendored_patterns: vendored,
             };
 
-            commands::count::run(config)?;
+            let stats = commands::count::run(config)?;
+            if !stats.errors.is_empty() {
+                // 1 is a failed run; 2 means some paths were skipped
+                std::process::exit(2);
+            }
         }
     }
 
@@ -0,0 +1,202 @@
+use globset::{GlobBuilder, GlobMatcher};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+/// One pattern line from a `.gitattributes` file.
+#[derive(Debug)]
+struct Rule {
+    matcher: GlobMatcher,
+    /// Patterns without a `/` match the file name at any depth.
+    basename_only: bool,
+    /// Attribute names with `Some(value)` when set or `None` when unspecified (`!attr`).
+    attributes: Vec<(String, Option<String>)>,
+}
+
+/// The linguist attributes of one path, each `"true"` when set, `"false"`
+/// when unset (`-attr`), the assigned value for `attr=value`, or `None`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LinguistAttributes {
+    pub language: Option<String>,
+    pub generated: Option<String>,
+    pub vendored: Option<String>,
+}
+
+/// Resolves `.gitattributes` entries for files below a scan root.
+/// Files are parsed lazily per directory and cached, so lookups can be
+/// shared between walker threads; once a directory is cached, lookups only
+/// take a read lock.
+#[derive(Debug)]
+pub struct GitAttributes {
+    root: PathBuf,
+    cache: RwLock<HashMap<PathBuf, Arc<Vec<Rule>>>>,
+}
+
+impl GitAttributes {
+    pub fn new(root: &Path) -> Self {
+        let root = if root.is_file() {
+            root.parent().unwrap_or(root)
+        } else {
+            root
+        };
+
+        Self {
+            root: root.to_path_buf(),
+            cache: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// The linguist attributes of a path, resolved in one pass over the
+    /// `.gitattributes` files above it.
+    /// Deeper `.gitattributes` files and later lines take precedence.
+    pub fn linguist(&self, path: &Path) -> LinguistAttributes {
+        let mut attributes = LinguistAttributes::default();
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return attributes;
+        };
+
+        let mut dir = self.root.clone();
+        let components: Vec<_> = relative.components().collect();
+        for (depth, component) in components.iter().enumerate() {
+            let rules = self.rules_for(&dir);
+            let below: PathBuf = components[depth..].iter().collect();
+
+            for rule in rules.iter() {
+                let matched = if rule.basename_only {
+                    path.file_name().is_some_and(|n| rule.matcher.is_match(n))
+                } else {
+                    rule.matcher.is_match(&below)
+                };
+                if !matched {
+                    continue;
+                }
+
+                for (attribute, value) in &rule.attributes {
+                    let slot = match attribute.as_str() {
+                        "linguist-language" => &mut attributes.language,
+                        "linguist-generated" => &mut attributes.generated,
+                        "linguist-vendored" => &mut attributes.vendored,
+                        _ => continue,
+                    };
+                    *slot = value.clone();
+                }
+            }
+
+            dir.push(component);
+        }
+
+        attributes
+    }
+
+    /// Parsed rules of the `.gitattributes` file in `dir`, if any.
+    /// The file is read outside the lock; if two threads race, the first
+    /// result is kept.
+    fn rules_for(&self, dir: &Path) -> Arc<Vec<Rule>> {
+        let cached = self
+            .cache
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(dir)
+            .cloned();
+        if let Some(rules) = cached {
+            return rules;
+        }
+
+        let content = fs::read_to_string(dir.join(".gitattributes")).unwrap_or_default();
+        let rules = Arc::new(parse(&content));
+        self.cache
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(dir.to_path_buf())
+            .or_insert(rules)
+            .clone()
+    }
+}
+
+/// Parse `.gitattributes` content; malformed patterns are skipped like git does.
+fn parse(content: &str) -> Vec<Rule> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                return None;
+            }
+
+            let mut words = line.split_whitespace();
+            let pattern = words.next()?;
+            let attributes = words
+                .map(|word| {
+                    if let Some(name) = word.strip_prefix('-') {
+                        (name.to_string(), Some("false".to_string()))
+                    } else if let Some(name) = word.strip_prefix('!') {
+                        (name.to_string(), None)
+                    } else if let Some((name, value)) = word.split_once('=') {
+                        (name.to_string(), Some(value.to_string()))
+                    } else {
+                        (word.to_string(), Some("true".to_string()))
+                    }
+                })
+                .collect();
+
+            let basename_only = !pattern.trim_end_matches('/').contains('/');
+            let glob = pattern.trim_start_matches('/');
+            let matcher = GlobBuilder::new(glob)
+                .literal_separator(true)
+                .build()
+                .ok()?
+                .compile_matcher();
+
+            Some(Rule {
+                matcher,
+                basename_only,
+                attributes,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::test_support::scratch_dir;
+
+    #[test]
+    fn test_linguist_language_lookup() {
+        let root = scratch_dir("attributes");
+        fs::create_dir_all(root.join("lib/legacy")).unwrap();
+        fs::write(
+            root.join(".gitattributes"),
+            r"# overrides
+*.inc linguist-language=PHP
+/lib/*.tpl linguist-language=HTML
+",
+        )
+        .unwrap();
+        fs::write(
+            root.join("lib/legacy/.gitattributes"),
+            "*.inc linguist-language=Perl -linguist-generated\n",
+        )
+        .unwrap();
+
+        let attributes = GitAttributes::new(&root);
+        let language = |path: &str| attributes.linguist(&root.join(path)).language;
+
+        assert_eq!(language("header.inc"), Some("PHP".to_string()));
+        assert_eq!(language("lib/header.inc"), Some("PHP".to_string()));
+        assert_eq!(language("lib/page.tpl"), Some("HTML".to_string()));
+        assert_eq!(language("page.tpl"), None);
+        assert_eq!(language("lib/legacy/header.inc"), Some("Perl".to_string()));
+        assert_eq!(
+            attributes.linguist(&root.join("lib/legacy/header.inc")),
+            LinguistAttributes {
+                language: Some("Perl".to_string()),
+                generated: Some("false".to_string()),
+                vendored: None,
+            }
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -1,30 +1,193 @@
 use crate::commands::count::CountConfig;
+use crate::stats::attributes::{GitAttributes, LinguistAttributes};
+use crate::stats::directories::DirectoryStats;
+use crate::stats::embedded::{self, Embedding};
+use crate::stats::encoding;
 use crate::stats::file_types::FileType;
-use ignore::WalkBuilder;
+use crate::stats::filters::{PathPatterns, ScanFilters};
+use crate::stats::generated::{self, Generated};
+use crate::stats::lexer::{LineKind, LineLexer};
+use crate::stats::notebook;
+use crate::stats::registry::LanguageRegistry;
+use crate::stats::sniff::{self, SNIFF_LEN};
+use crate::stats::test_code::{TestBlocks, DEFAULT_TEST_PATTERNS};
+use crate::stats::vendored::{VendoredStats, DEFAULT_VENDORED_PATTERNS};
+use crate::types::{FileSort, IgnorePolicy, MappingTarget, MixedPolicy};
+use ignore::{DirEntry, ParallelVisitor, ParallelVisitorBuilder, WalkBuilder, WalkState};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::fs;
-use std::path::Path;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct ProjectStats {
     pub total_files: usize,
     pub total_lines: usize,
     pub total_code_lines: usize,
     pub total_comment_lines: usize,
+    /// Documentation comments (`///`, `/** */`, docstrings), not part of `total_comment_lines`.
+    pub total_doc_comment_lines: usize,
     pub total_blank_lines: usize,
+    pub total_mixed_lines: usize,
+    /// Lines found in regions embedded in other languages' files, such as `<script>` blocks.
+    pub total_embedded_lines: usize,
+    /// Cells across all Jupyter notebooks.
+    pub total_notebook_cells: usize,
+    /// Files that were decoded from something other than UTF-8.
+    pub total_transcoded_files: usize,
+    /// Files skipped because another symlink or hard link to them was already counted.
+    pub total_duplicate_files: usize,
+    /// Generated and minified files; they are also part of `total_files`.
+    pub total_generated_files: usize,
+    /// Lines of generated files; they are also part of `total_lines`.
+    pub total_generated_lines: usize,
+    /// Lines of test code; the rest of `total_lines` is production code.
+    pub total_test_lines: usize,
     pub file_types: HashMap<String, FileTypeStats>,
     pub total_size_bytes: u64,
+    /// One record per file, only collected with `--by-file`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<FileStats>,
+    /// Per-directory rollup, only collected with `--by-dir`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub directories: Option<DirectoryStats>,
+    /// Files and directories that could not be read; the scan skipped them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<ScanError>,
+    /// The path and language filters the scan ran with.
+    #[serde(default)]
+    pub filters: ScanFilters,
+    /// The ignore files the scan applied.
+    #[serde(default)]
+    pub ignore_policy: IgnorePolicy,
+    /// Third-party code, counted apart from and not included in the totals above.
+    #[serde(default)]
+    pub vendored: VendoredStats,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FileTypeStats {
     pub count: usize,
     pub lines: usize,
     pub code_lines: usize,
     pub comment_lines: usize,
+    /// Documentation comments, not part of `comment_lines`.
+    pub doc_comment_lines: usize,
     pub blank_lines: usize,
+    /// Lines holding both code and a comment, whatever `MixedPolicy` counted them as.
+    pub mixed_lines: usize,
+    /// Lines of this language found embedded in other languages' files.
+    pub embedded_lines: usize,
+    /// Cells in the notebooks of this type.
+    pub notebook_cells: usize,
+    /// Generated and minified files, also part of `count`.
+    pub generated_files: usize,
+    /// Lines of this language in generated files, also part of `lines`.
+    pub generated_lines: usize,
+    /// Lines of test code, by path convention or in-file test blocks.
+    /// Production lines are `lines - test_lines`.
+    pub test_lines: usize,
     pub size_bytes: u64,
+    /// Number of text files per detected encoding.
+    pub encodings: BTreeMap<String, usize>,
+    /// Files that were decoded from something other than UTF-8.
+    pub transcoded_files: usize,
+}
+
+/// Counts for a single file. Lines of embedded regions and notebook cells
+/// are included, and the file is listed under its host language.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileStats {
+    pub path: String,
+    pub language: String,
+    pub lines: usize,
+    pub code_lines: usize,
+    pub comment_lines: usize,
+    /// Documentation comments, not part of `comment_lines`.
+    pub doc_comment_lines: usize,
+    pub blank_lines: usize,
+    /// Lines holding both code and a comment, whatever `MixedPolicy` counted them as.
+    pub mixed_lines: usize,
+    pub test_lines: usize,
+    pub notebook_cells: usize,
+    pub generated: bool,
+    /// Counted under `ProjectStats::vendored` rather than the project totals.
+    pub vendored: bool,
+    pub size_bytes: u64,
+}
+
+/// A path the scan could not read, with the kind of failure.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanError {
+    pub path: Option<String>,
+    /// `io::ErrorKind` name such as `PermissionDenied`, `FilesystemLoop`, or `Other`.
+    pub kind: String,
+    pub message: String,
+}
+
+impl ScanError {
+    fn new(error: &ignore::Error) -> Self {
+        let mut path = None;
+        let mut error = error;
+        loop {
+            match error {
+                ignore::Error::WithPath {
+                    path: error_path,
+                    err,
+                } => {
+                    path.get_or_insert_with(|| error_path.to_string_lossy().into_owned());
+                    error = err.as_ref();
+                }
+                ignore::Error::WithDepth { err, .. }
+                | ignore::Error::WithLineNumber { err, .. } => {
+                    error = err.as_ref();
+                }
+                _ => break,
+            }
+        }
+
+        let kind = match error {
+            ignore::Error::Io(e) => format!("{:?}", e.kind()),
+            ignore::Error::Loop { child, .. } => {
+                path.get_or_insert_with(|| child.to_string_lossy().into_owned());
+                "FilesystemLoop".to_string()
+            }
+            _ => "Other".to_string(),
+        };
+
+        Self {
+            path,
+            kind,
+            message: error.to_string(),
+        }
+    }
+}
+
+impl FileTypeStats {
+    /// Fold another language's counts into this one.
+    pub fn merge(&mut self, other: FileTypeStats) {
+        self.count += other.count;
+        self.lines += other.lines;
+        self.code_lines += other.code_lines;
+        self.comment_lines += other.comment_lines;
+        self.doc_comment_lines += other.doc_comment_lines;
+        self.blank_lines += other.blank_lines;
+        self.mixed_lines += other.mixed_lines;
+        self.embedded_lines += other.embedded_lines;
+        self.notebook_cells += other.notebook_cells;
+        self.generated_files += other.generated_files;
+        self.generated_lines += other.generated_lines;
+        self.test_lines += other.test_lines;
+        self.size_bytes += other.size_bytes;
+        self.transcoded_files += other.transcoded_files;
+
+        for (encoding, count) in other.encodings {
+            *self.encodings.entry(encoding).or_default() += count;
+        }
+    }
 }
 
 impl Default for ProjectStats {
@@ -40,14 +203,58 @@ impl ProjectStats {
             total_lines: 0,
             total_code_lines: 0,
             total_comment_lines: 0,
+            total_doc_comment_lines: 0,
             total_blank_lines: 0,
+            total_mixed_lines: 0,
+            total_embedded_lines: 0,
+            total_notebook_cells: 0,
+            total_transcoded_files: 0,
+            total_duplicate_files: 0,
+            total_generated_files: 0,
+            total_generated_lines: 0,
+            total_test_lines: 0,
             file_types: HashMap::new(),
             total_size_bytes: 0,
+            files: Vec::new(),
+            directories: None,
+            errors: Vec::new(),
+            filters: ScanFilters::default(),
+            ignore_policy: IgnorePolicy::default(),
+            vendored: VendoredStats::default(),
         }
     }
 
     /// Recursively scan directory and collect file statistics.
-    /// Respects .gitignore files and hidden file preferences.
+    /// Respects ignore files as set by `config.ignore` and hidden file preferences.
+    ///
+    /// The walk runs on `config.threads` workers (0 picks a count automatically).
+    /// Each worker fills its own `ProjectStats`, which are merged once the walk ends.
+    ///
+    /// Paths that cannot be read are recorded in `errors` and skipped; with
+    /// `config.strict` the first one stops the scan and is returned instead.
+    ///
+    /// Symlinks are only followed with `config.follow_symlinks`, and links back
+    /// to an ancestor directory are skipped. A file reachable through several
+    /// links is counted once, by device and inode: through its path without
+    /// symlinks when the walk reaches one, otherwise through its smallest path.
+    /// Only files reached through a symlink or with several hard links are set
+    /// aside during the walk and counted afterwards, so which link wins (and
+    /// whether it is vendored or which directory it rolls up into) does not
+    /// depend on thread scheduling.
+    ///
+    /// Files under vendored paths (`vendor/`, `third_party/`, ... and
+    /// `config.vendored_patterns`) or marked `linguist-vendored` are counted
+    /// in `vendored` instead of the totals; `-linguist-vendored` overrules the paths.
+    ///
+    /// `--include` and `--exclude` globs prune the walk itself, so filtered
+    /// files are never opened. Language filters are checked against the
+    /// language from the file name or extension before reading, unless the
+    /// content may still change it (disambiguated extensions, plain text) or
+    /// embeds other languages; modelines cannot bring back a file left out
+    /// this way. Otherwise they apply after the content heuristics, and to
+    /// each embedded region on its own. Files they leave out count towards
+    /// nothing, not even `total_files`; a left-out host page still
+    /// contributes the lines of allowed regions.
     pub fn scan_directory(
         &mut self,
         path: &Path,
@@ -57,105 +264,546 @@ impl ProjectStats {
 
         builder
             .hidden(!config.include_hidden)
-            .git_ignore(config.respect_gitignore)
-            .git_exclude(config.respect_gitignore);
+            .git_ignore(config.ignore.gitignore)
+            .git_exclude(config.ignore.gitignore)
+            .git_global(config.ignore.global_gitignore)
+            .ignore(config.ignore.ignore_files)
+            .parents(config.ignore.parents)
+            .follow_links(config.follow_symlinks)
+            .threads(config.threads);
+        if config.ignore.tallyhawkignore {
+            builder.add_custom_ignore_filename(TALLYHAWK_IGNORE);
+        }
+        self.ignore_policy = config.ignore;
+        if config.verbose {
+            eprintln!("ignore sources: {}", ignore_sources(config).join(", "));
+        }
+
+        let registry = LanguageRegistry::load(config.languages.as_deref())?;
+        let filters = ScanFilters::new(
+            &config.include,
+            &config.exclude,
+            &config.only_languages,
+            &config.excluded_languages,
+            registry,
+        )?;
+        if !(filters.include.is_empty() && filters.exclude.is_empty()) {
+            let path_filter = filters.path_filter()?;
+            let root = path.to_path_buf();
+            builder.filter_entry(move |entry| {
+                let relative_path = entry.path().strip_prefix(&root).unwrap_or(entry.path());
+                let is_dir = entry.file_type().is_some_and(|ft| ft.is_dir());
+                path_filter.allows(relative_path, is_dir)
+            });
+        }
+        self.filters = filters.clone();
+
+        let vendored_patterns: Vec<String> = DEFAULT_VENDORED_PATTERNS
+            .iter()
+            .map(|pattern| pattern.to_string())
+            .chain(config.vendored_patterns.iter().cloned())
+            .collect();
+        let vendored = PathPatterns::new(&vendored_patterns)?;
+        let test_patterns: Vec<String> = DEFAULT_TEST_PATTERNS
+            .iter()
+            .map(|pattern| pattern.to_string())
+            .collect();
+        let tests = PathPatterns::new(&test_patterns)?;
+        self.vendored.patterns = vendored_patterns;
+        let mut mappings = HashMap::new();
+        for mapping in &config.mappings {
+            if let MappingTarget::Language(name) = &mapping.target {
+                if registry.lookup_name(name).is_none() {
+                    return Err(format!(
+                        "unknown language '{}' in --map {}={}",
+                        name, mapping.extension, name
+                    )
+                    .into());
+                }
+            }
+            mappings.insert(mapping.extension.clone(), mapping.target.clone());
+        }
 
-        for result in builder.build() {
-            let entry = result?;
+        if config.by_dir {
+            self.directories.get_or_insert_with(DirectoryStats::default);
+        }
+
+        let context = ScanContext {
+            root: path.to_path_buf(),
+            config,
+            attributes: GitAttributes::new(path),
+            registry,
+            mappings,
+            filters,
+            vendored,
+            tests,
+            links: LinkTracker::new(config.follow_symlinks),
+        };
+        let results = Mutex::new(Vec::new());
+        builder.build_parallel().visit(&mut WorkerBuilder {
+            context: &context,
+            results: &results,
+        });
 
-            if entry.file_type().is_some_and(|ft| ft.is_file()) {
-                self.process_file(entry.path(), config)?;
+        let mut first_error = None;
+        for result in results.into_inner().unwrap_or_else(|e| e.into_inner()) {
+            match result {
+                Ok(stats) => self.merge(stats),
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
             }
         }
 
+        let counted_links = mem::take(
+            &mut *context
+                .links
+                .counted
+                .lock()
+                .unwrap_or_else(|e| e.into_inner()),
+        );
+        let deferred_links = mem::take(
+            &mut *context
+                .links
+                .deferred
+                .lock()
+                .unwrap_or_else(|e| e.into_inner()),
+        );
+        for (key, mut paths) in deferred_links {
+            if counted_links.contains(&key) {
+                self.total_duplicate_files += paths.len();
+                continue;
+            }
+            if first_error.is_some() {
+                break;
+            }
+            paths.sort();
+            self.total_duplicate_files += paths.len() - 1;
+            match self.count_file(&paths[0], &context) {
+                Ok(()) => {}
+                Err(e) if config.strict => first_error = Some(e),
+                Err(e) => self.errors.push(ScanError::new(&e)),
+            }
+        }
+
+        // Workers finish in any order; keep the per-file lists stable
+        self.files.sort_by(|a, b| a.path.cmp(&b.path));
+        self.errors.sort_by(|a, b| a.path.cmp(&b.path));
+
+        match first_error {
+            Some(e) => Err(e.into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Fold the totals and per-language counts of another scan into this one.
+    pub fn merge(&mut self, other: ProjectStats) {
+        self.total_files += other.total_files;
+        self.total_lines += other.total_lines;
+        self.total_code_lines += other.total_code_lines;
+        self.total_comment_lines += other.total_comment_lines;
+        self.total_doc_comment_lines += other.total_doc_comment_lines;
+        self.total_blank_lines += other.total_blank_lines;
+        self.total_mixed_lines += other.total_mixed_lines;
+        self.total_embedded_lines += other.total_embedded_lines;
+        self.total_notebook_cells += other.total_notebook_cells;
+        self.total_transcoded_files += other.total_transcoded_files;
+        self.total_duplicate_files += other.total_duplicate_files;
+        self.total_generated_files += other.total_generated_files;
+        self.total_generated_lines += other.total_generated_lines;
+        self.total_test_lines += other.total_test_lines;
+        self.total_size_bytes += other.total_size_bytes;
+
+        for (language, file_stats) in other.file_types {
+            self.file_types
+                .entry(language)
+                .or_default()
+                .merge(file_stats);
+        }
+        self.files.extend(other.files);
+        self.errors.extend(other.errors);
+        self.vendored.merge(other.vendored);
+        if let Some(directories) = other.directories {
+            self.directories
+                .get_or_insert_with(DirectoryStats::default)
+                .merge(directories);
+        }
+    }
+
+    /// Order the per-file records for display.
+    pub fn sort_files(&mut self, sort: FileSort) {
+        self.files.sort_by(|a, b| {
+            let order = match sort {
+                FileSort::Lines => b.lines.cmp(&a.lines),
+                FileSort::Code => b.code_lines.cmp(&a.code_lines),
+                FileSort::Comments => b.comment_lines.cmp(&a.comment_lines),
+                FileSort::Blanks => b.blank_lines.cmp(&a.blank_lines),
+                FileSort::Size => b.size_bytes.cmp(&a.size_bytes),
+                FileSort::Path => std::cmp::Ordering::Equal,
+            };
+            order.then_with(|| a.path.cmp(&b.path))
+        });
+    }
+
+    /// Count one file found by the walk, keeping vendored files apart and
+    /// adding the rest to their directories with `--by-dir`.
+    fn count_file(&mut self, path: &Path, context: &ScanContext) -> Result<(), ignore::Error> {
+        let config = context.config;
+        let attributes = context.attributes.linguist(path);
+        let vendored = context.is_vendored(path, &attributes);
+        if vendored || config.by_dir {
+            // Count the file on its own so its counts can be kept apart or
+            // added to each directory above it
+            let mut file_stats = ProjectStats::new();
+            file_stats
+                .process_file(path, context, &attributes)
+                .map_err(|e| with_path(e, path))?;
+
+            if vendored {
+                for file in &mut file_stats.files {
+                    file.vendored = true;
+                }
+                self.files.append(&mut file_stats.files);
+                self.vendored.add(file_stats);
+                return Ok(());
+            }
+
+            let relative_path = path.strip_prefix(&context.root).unwrap_or(path);
+            self.directories
+                .get_or_insert_with(DirectoryStats::default)
+                .add_file(relative_path, config.depth, &file_stats.file_types);
+            self.merge(file_stats);
+        } else {
+            self.process_file(path, context, &attributes)
+                .map_err(|e| with_path(e, path))?;
+        }
         Ok(())
     }
 
     /// Process a single file: count lines, detect type, measure size.
     /// Binary files are tracked but not analyzed for line content.
+    /// Only the first `SNIFF_LEN` bytes are read until the content is known to be text.
+    /// Files embedding other languages (HTML, Markdown, Vue, Svelte) and
+    /// Jupyter notebooks are split into regions, each counted under its own language.
+    ///
+    /// Language detection precedence, highest first: `--map` extension
+    /// overrides, `.gitattributes` `linguist-language`, editor modeline, file
+    /// name, content heuristics for shared extensions, extension, and finally
+    /// the shebang line for files nothing else recognised.
+    ///
+    /// Generated files are recognised by `linguist-generated`, which also
+    /// overrules the heuristics when unset, then by file name, header markers
+    /// and minified content. They are counted as usual and also as generated,
+    /// or skipped entirely with `config.exclude_generated`.
     fn process_file(
         &mut self,
         path: &Path,
-        config: &CountConfig,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+        context: &ScanContext,
+        attributes: &LinguistAttributes,
+    ) -> io::Result<()> {
+        let config = context.config;
+        let registry = context.registry;
+        let mapping = context.mapping_for(path);
+        if mapping == Some(&MappingTarget::Ignore) {
+            return Ok(());
+        }
+
+        let generated_attribute = attributes.generated.as_deref();
+        let mut generated = match generated_attribute {
+            Some("true") => Some(Generated::Attribute),
+            Some(_) => None,
+            None => generated::detect_path(path),
+        };
+        if generated.is_some() && config.exclude_generated {
+            return Ok(());
+        }
+
         let metadata = fs::metadata(path)?;
         let file_size = metadata.len();
 
-        let file_type = FileType::from_path(path);
+        let mapped_type = match mapping {
+            Some(MappingTarget::Binary) => {
+                let file_type = FileType::binary("Binary");
+                if context.filters.allows_language(file_type.language()) {
+                    self.add_binary_file(path, &file_type, file_size, config);
+                }
+                return Ok(());
+            }
+            Some(MappingTarget::Language(name)) => registry.lookup_name(name),
+            _ => None,
+        };
+        let attribute_type = mapped_type.or_else(|| {
+            attributes
+                .language
+                .as_deref()
+                .and_then(|name| registry.lookup_name(name))
+        });
+        let is_overridden = attribute_type.is_some();
+        let mut file_type = attribute_type.unwrap_or_else(|| registry.detect_path(path));
+
+        // Unless the content may still pick another language or embed
+        // others, files the language filters leave out need not be opened
+        let content_may_change = !is_overridden
+            && (file_type.embeds.is_some()
+                || file_type.is_plain_text()
+                || registry
+                    .disambiguation_languages(path)
+                    .into_iter()
+                    .any(|language| context.filters.allows_language(language)));
+        if !content_may_change && !context.filters.allows_language(file_type.language()) {
+            return Ok(());
+        }
 
         // Skip binary files for line counting
-        if file_type.is_binary() {
-            self.add_binary_file(&file_type, file_size);
+        if !file_type.needs_content_check() {
+            if context.filters.allows_language(file_type.language()) {
+                self.add_binary_file(path, &file_type, file_size, config);
+            }
             return Ok(());
         }
 
-        let content = match fs::read_to_string(path) {
-            Ok(content) => content,
-            Err(e) => {
-                // If we can't read as UTF-8, treat as binary
-                if e.kind() == std::io::ErrorKind::InvalidData {
-                    self.add_binary_file(&file_type, file_size);
-                    return Ok(());
+        let mut file = File::open(path)?;
+        let mut bytes = Vec::with_capacity(SNIFF_LEN);
+        file.by_ref()
+            .take(SNIFF_LEN as u64)
+            .read_to_end(&mut bytes)?;
+
+        // UTF-16 is full of NUL bytes, so the encoding is sniffed before binary content
+        let sniffed_encoding = encoding::sniff(&bytes);
+        if sniffed_encoding.is_none() {
+            if let Some(kind) = sniff::binary_kind(&bytes) {
+                let file_type = FileType::binary(kind);
+                if context.filters.allows_language(file_type.language()) {
+                    self.add_binary_file(path, &file_type, file_size, config);
+                }
+                return Ok(());
+            }
+        }
+        if file_type.is_binary() {
+            // The extension guessed binary, but the content is text
+            file_type = registry.text();
+        }
+
+        file.read_to_end(&mut bytes)?;
+        let (content, encoding) =
+            encoding::decode(bytes, sniffed_encoding, config.fallback_encoding);
+        if !is_overridden {
+            if let Some(declared_type) = registry.detect_modeline(&content) {
+                file_type = declared_type;
+            } else if let Some(found) = registry.disambiguate(path, &content) {
+                if config.verbose {
+                    eprintln!("{}: {}", path.display(), found);
+                }
+                file_type = found.file_type;
+            } else if file_type.is_plain_text() {
+                // Extensionless scripts announce their interpreter on the first line
+                if let Some(script_type) = content
+                    .lines()
+                    .next()
+                    .and_then(|line| registry.detect_shebang(line))
+                {
+                    file_type = script_type;
                 }
-                // Re-throw other IO errors
-                return Err(e.into());
             }
+        }
+
+        // A host language left out may still embed regions that are counted
+        let host_allowed = context.filters.allows_language(file_type.language());
+        if !host_allowed && file_type.embeds.is_none() {
+            return Ok(());
+        }
+
+        if generated_attribute.is_none() && generated.is_none() {
+            generated = generated::detect_content(&content);
+        }
+        if let Some(reason) = generated {
+            if config.verbose {
+                eprintln!("{}: {}", path.display(), reason);
+            }
+            if config.exclude_generated {
+                return Ok(());
+            }
+            if host_allowed {
+                self.total_generated_files += 1;
+            }
+        }
+        let is_generated = generated.is_some();
+        let is_test = context.is_test_path(path);
+
+        let mut cells = 0;
+        let regions = match file_type.embeds {
+            Some(Embedding::Notebook) => {
+                let (regions, notebook_cells) = notebook::split(&content, &file_type, registry);
+                cells = notebook_cells;
+                Some(regions)
+            }
+            Some(embedding) => Some(embedded::split(&content, &file_type, embedding, registry)),
+            None => None,
         };
-        let line_stats = self.analyze_lines(&content, &file_type, config);
+
+        let mut file_lines = LineStats::default();
+        match regions {
+            Some(regions) => {
+                for region in regions {
+                    if !context.filters.allows_language(region.file_type.language()) {
+                        continue;
+                    }
+                    let mut line_stats =
+                        self.analyze_lines(&region.content, &region.file_type, config);
+                    if is_test {
+                        line_stats.test = line_stats.total;
+                    }
+                    self.add_lines(region.file_type.language(), &line_stats, region.embedded);
+                    if is_generated {
+                        self.add_generated_lines(region.file_type.language(), line_stats.total);
+                    }
+                    file_lines.add(&line_stats);
+                }
+            }
+            None => {
+                file_lines = self.analyze_lines(&content, &file_type, config);
+                if is_test {
+                    file_lines.test = file_lines.total;
+                }
+                self.add_lines(file_type.language(), &file_lines, false);
+                if is_generated {
+                    self.add_generated_lines(file_type.language(), file_lines.total);
+                }
+            }
+        }
+
+        // The file itself counts towards its host language only
+        if !host_allowed {
+            return Ok(());
+        }
 
         self.total_files += 1;
-        self.total_lines += line_stats.total;
-        self.total_code_lines += line_stats.code;
-        self.total_comment_lines += line_stats.comments;
-        self.total_blank_lines += line_stats.blank;
         self.total_size_bytes += file_size;
+        self.total_notebook_cells += cells;
+        if encoding.is_transcoded() {
+            self.total_transcoded_files += 1;
+        }
 
         let language_key = file_type.language().to_string();
-        let entry = self
-            .file_types
-            .entry(language_key)
-            .or_insert(FileTypeStats {
-                count: 0,
-                lines: 0,
-                code_lines: 0,
-                comment_lines: 0,
-                blank_lines: 0,
-                size_bytes: 0,
-            });
+        let entry = self.file_types.entry(language_key).or_default();
 
         entry.count += 1;
+        entry.size_bytes += file_size;
+        entry.notebook_cells += cells;
+        *entry
+            .encodings
+            .entry(encoding.name().to_string())
+            .or_default() += 1;
+        if encoding.is_transcoded() {
+            entry.transcoded_files += 1;
+        }
+        if is_generated {
+            entry.generated_files += 1;
+        }
+
+        if config.by_file {
+            self.add_file_record(
+                path,
+                &file_type,
+                &file_lines,
+                cells,
+                is_generated,
+                file_size,
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Keep the counts of one file for the `--by-file` report.
+    fn add_file_record(
+        &mut self,
+        path: &Path,
+        file_type: &FileType,
+        line_stats: &LineStats,
+        notebook_cells: usize,
+        generated: bool,
+        size: u64,
+    ) {
+        self.files.push(FileStats {
+            path: path.to_string_lossy().into_owned(),
+            language: file_type.language().to_string(),
+            lines: line_stats.total,
+            code_lines: line_stats.code,
+            comment_lines: line_stats.comments,
+            doc_comment_lines: line_stats.doc_comments,
+            blank_lines: line_stats.blank,
+            mixed_lines: line_stats.mixed,
+            test_lines: line_stats.test,
+            notebook_cells,
+            generated,
+            vendored: false,
+            size_bytes: size,
+        });
+    }
+
+    /// Mark lines already added with `add_lines` as coming from a generated file.
+    fn add_generated_lines(&mut self, language: &str, lines: usize) {
+        self.total_generated_lines += lines;
+        self.file_types
+            .entry(language.to_string())
+            .or_default()
+            .generated_lines += lines;
+    }
+
+    /// Add analyzed lines to the totals and to one language.
+    /// Embedded lines count towards the language they are written in,
+    /// not the file that holds them.
+    fn add_lines(&mut self, language: &str, line_stats: &LineStats, embedded: bool) {
+        self.total_lines += line_stats.total;
+        self.total_code_lines += line_stats.code;
+        self.total_comment_lines += line_stats.comments;
+        self.total_doc_comment_lines += line_stats.doc_comments;
+        self.total_blank_lines += line_stats.blank;
+        self.total_mixed_lines += line_stats.mixed;
+        self.total_test_lines += line_stats.test;
+
+        let entry = self.file_types.entry(language.to_string()).or_default();
         entry.lines += line_stats.total;
         entry.code_lines += line_stats.code;
         entry.comment_lines += line_stats.comments;
+        entry.doc_comment_lines += line_stats.doc_comments;
         entry.blank_lines += line_stats.blank;
-        entry.size_bytes += file_size;
+        entry.mixed_lines += line_stats.mixed;
+        entry.test_lines += line_stats.test;
 
-        Ok(())
+        if embedded {
+            self.total_embedded_lines += line_stats.total;
+            entry.embedded_lines += line_stats.total;
+        }
     }
 
     /// Track binary files (images, executables, etc.) without line analysis.
-    fn add_binary_file(&mut self, file_type: &FileType, size: u64) {
+    fn add_binary_file(
+        &mut self,
+        path: &Path,
+        file_type: &FileType,
+        size: u64,
+        config: &CountConfig,
+    ) {
         self.total_files += 1;
         self.total_size_bytes += size;
 
         let language_key = file_type.language().to_string();
-        let entry = self
-            .file_types
-            .entry(language_key)
-            .or_insert(FileTypeStats {
-                count: 0,
-                lines: 0,
-                code_lines: 0,
-                comment_lines: 0,
-                blank_lines: 0,
-                size_bytes: 0,
-            });
+        let entry = self.file_types.entry(language_key).or_default();
 
         entry.count += 1;
         entry.size_bytes += size;
+
+        if config.by_file {
+            self.add_file_record(path, file_type, &LineStats::default(), 0, false, size);
+        }
     }
 
-    /// Analyze file content line by line: categorize as code, comments, or blanks.
+    /// Analyze file content line by line: categorize as code, comments, doc comments, or blanks.
+    /// Block comments are followed across lines by a per-file `LineLexer`.
+    /// Lines with both code and a comment are counted according to `config.mixed_as`.
+    /// Lines inside test blocks (`#[cfg(test)]`) that count towards the total are test lines.
     fn analyze_lines(
         &self,
         content: &str,
@@ -165,20 +813,43 @@ impl ProjectStats {
         let lines: Vec<&str> = content.lines().collect();
         let mut stats = LineStats {
             total: lines.len(),
-            code: 0,
-            comments: 0,
-            blank: 0,
+            ..Default::default()
         };
 
+        let mut lexer = LineLexer::new(file_type);
+        let mut test_blocks = TestBlocks::new(&file_type.test_markers);
         for line in lines {
-            let trimmed = line.trim();
+            let kind = lexer.classify(line);
+            if test_blocks.is_test_line(line) {
+                let counted = match kind {
+                    LineKind::Blank => config.include_blank_lines,
+                    LineKind::Comment | LineKind::DocComment => config.include_comments,
+                    LineKind::Mixed => {
+                        config.include_comments || config.mixed_as != MixedPolicy::Comment
+                    }
+                    LineKind::Code => true,
+                };
+                if counted {
+                    stats.test += 1;
+                }
+            }
 
-            if trimmed.is_empty() {
-                stats.blank += 1;
-            } else if file_type.is_comment_line(trimmed) {
-                stats.comments += 1;
-            } else {
-                stats.code += 1;
+            match kind {
+                LineKind::Blank => stats.blank += 1,
+                LineKind::Comment => stats.comments += 1,
+                LineKind::DocComment => stats.doc_comments += 1,
+                LineKind::Code => stats.code += 1,
+                LineKind::Mixed => {
+                    stats.mixed += 1;
+                    match config.mixed_as {
+                        MixedPolicy::Code => stats.code += 1,
+                        MixedPolicy::Comment => stats.comments += 1,
+                        MixedPolicy::Both => {
+                            stats.code += 1;
+                            stats.comments += 1;
+                        }
+                    }
+                }
             }
         }
 
@@ -186,16 +857,1073 @@ impl ProjectStats {
             stats.total -= stats.blank;
         }
         if !config.include_comments {
-            stats.total -= stats.comments;
+            // Mixed lines counted as both still hold code, so they stay in the total.
+            stats.total -= stats.doc_comments
+                + match config.mixed_as {
+                    MixedPolicy::Both => stats.comments - stats.mixed,
+                    _ => stats.comments,
+                };
         }
 
         stats
     }
 }
 
+/// Project-specific ignore file, read in every directory like `.gitignore`.
+pub const TALLYHAWK_IGNORE: &str = ".tallyhawkignore";
+
+/// The ignore files and rules the walker applies, in the walker's own terms.
+fn ignore_sources(config: &CountConfig) -> Vec<&'static str> {
+    let policy = config.ignore;
+    let mut sources = Vec::new();
+    if policy.gitignore {
+        sources.extend([".gitignore", ".git/info/exclude"]);
+    }
+    if policy.global_gitignore {
+        sources.push("global gitignore");
+    }
+    if policy.ignore_files {
+        sources.push(".ignore");
+    }
+    if policy.tallyhawkignore {
+        sources.push(TALLYHAWK_IGNORE);
+    }
+    if policy.parents && !sources.is_empty() {
+        sources.push("parent directories");
+    }
+    if !config.include_hidden {
+        sources.insert(0, "hidden files");
+    }
+    if sources.is_empty() {
+        sources.push("none");
+    }
+    sources
+}
+
+/// State shared by all walker threads for the duration of one scan.
+struct ScanContext<'a> {
+    /// The path the scan started from; `--by-dir` paths are relative to it.
+    root: PathBuf,
+    config: &'a CountConfig,
+    attributes: GitAttributes,
+    registry: &'static LanguageRegistry,
+    /// `--map` overrides by lowercase extension.
+    mappings: HashMap<String, MappingTarget>,
+    filters: ScanFilters,
+    /// Paths holding third-party code.
+    vendored: PathPatterns,
+    /// Paths holding tests by convention.
+    tests: PathPatterns,
+    links: LinkTracker,
+}
+
+impl ScanContext<'_> {
+    /// The `--map` override for a file's extension, if any.
+    fn mapping_for(&self, path: &Path) -> Option<&MappingTarget> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        self.mappings.get(&extension)
+    }
+
+    /// Whether a file is third-party code, by `linguist-vendored` or its path.
+    fn is_vendored(&self, path: &Path, attributes: &LinguistAttributes) -> bool {
+        match attributes.vendored.as_deref() {
+            Some("true") => true,
+            Some(_) => false,
+            None => {
+                let relative_path = path.strip_prefix(&self.root).unwrap_or(path);
+                self.vendored.is_match_or_parent(relative_path)
+            }
+        }
+    }
+
+    /// Whether a whole file is test code by its path.
+    fn is_test_path(&self, path: &Path) -> bool {
+        let relative_path = path.strip_prefix(&self.root).unwrap_or(path);
+        self.tests.is_match_or_parent(relative_path)
+    }
+}
+
+/// Device and inode of a file.
+type FileKey = (u64, u64);
+
+/// How a worker handles a file that may be reachable through several paths.
+#[derive(Debug, PartialEq, Eq)]
+enum Claim {
+    /// Count it now; no other path can lead to it.
+    Unlinked,
+    /// Count it now and remember its key, since a symlink may lead to it too.
+    Counted(FileKey),
+    /// Leave it for after the walk.
+    Deferred,
+}
+
+/// Files that may be reached through more than one path during a scan.
+/// Files on a path without symlinks and with a single hard link are counted
+/// by the workers; the rest are deferred and grouped by device and inode.
+struct LinkTracker {
+    follow_symlinks: bool,
+    /// Directories the walk entered through a symlink.
+    symlinked_dirs: RwLock<Vec<PathBuf>>,
+    /// Keys of files the workers counted while following symlinks.
+    counted: Mutex<HashSet<FileKey>>,
+    /// Paths of deferred files.
+    deferred: Mutex<HashMap<FileKey, Vec<PathBuf>>>,
+}
+
+impl LinkTracker {
+    fn new(follow_symlinks: bool) -> Self {
+        LinkTracker {
+            follow_symlinks,
+            symlinked_dirs: RwLock::new(Vec::new()),
+            counted: Mutex::new(HashSet::new()),
+            deferred: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Remember a directory if the walk entered it through a symlink.
+    /// The walker visits a directory before any of its entries.
+    fn visit_dir(&self, entry: &DirEntry) {
+        if self.follow_symlinks && entry.depth() > 0 && entry.path_is_symlink() {
+            self.symlinked_dirs
+                .write()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(entry.path().to_path_buf());
+        }
+    }
+
+    /// Whether a worker counts a file now, deferring it if it was reached
+    /// through a symlink or has several hard links.
+    #[cfg(unix)]
+    fn claim(&self, entry: &DirEntry) -> Result<Claim, ignore::Error> {
+        use std::os::unix::fs::MetadataExt;
+
+        let metadata = entry.metadata()?;
+        let key = (metadata.dev(), metadata.ino());
+        let through_symlink = self.follow_symlinks
+            && (entry.path_is_symlink()
+                || self
+                    .symlinked_dirs
+                    .read()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .iter()
+                    .any(|dir| entry.path().starts_with(dir)));
+        if through_symlink || metadata.nlink() > 1 {
+            self.deferred
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .entry(key)
+                .or_default()
+                .push(entry.path().to_path_buf());
+            return Ok(Claim::Deferred);
+        }
+
+        Ok(if self.follow_symlinks {
+            Claim::Counted(key)
+        } else {
+            Claim::Unlinked
+        })
+    }
+
+    #[cfg(not(unix))]
+    fn claim(&self, _entry: &DirEntry) -> Result<Claim, ignore::Error> {
+        Ok(Claim::Unlinked)
+    }
+}
+
+/// Hands out one `Worker` per walker thread.
+struct WorkerBuilder<'s> {
+    context: &'s ScanContext<'s>,
+    results: &'s Mutex<Vec<Result<ProjectStats, ignore::Error>>>,
+}
+
+impl<'s> ParallelVisitorBuilder<'s> for WorkerBuilder<'s> {
+    fn build(&mut self) -> Box<dyn ParallelVisitor + 's> {
+        Box::new(Worker {
+            context: self.context,
+            results: self.results,
+            stats: ProjectStats::new(),
+            counted_links: HashSet::new(),
+            error: None,
+        })
+    }
+}
+
+/// Per-thread visitor that accumulates into its own `ProjectStats`.
+/// The stats are handed back when the walker drops the visitor.
+struct Worker<'s> {
+    context: &'s ScanContext<'s>,
+    results: &'s Mutex<Vec<Result<ProjectStats, ignore::Error>>>,
+    stats: ProjectStats,
+    /// Keys of files counted while following symlinks.
+    counted_links: HashSet<FileKey>,
+    error: Option<ignore::Error>,
+}
+
+impl Worker<'_> {
+    fn visit_entry(&mut self, entry: DirEntry) -> Result<(), ignore::Error> {
+        let Some(file_type) = entry.file_type() else {
+            return Ok(());
+        };
+        if file_type.is_dir() {
+            self.context.links.visit_dir(&entry);
+            return Ok(());
+        }
+        if !file_type.is_file() {
+            return Ok(());
+        }
+
+        match self.context.links.claim(&entry)? {
+            Claim::Unlinked => {}
+            Claim::Counted(key) => {
+                self.counted_links.insert(key);
+            }
+            // Counted once the walk is over
+            Claim::Deferred => return Ok(()),
+        }
+
+        self.stats.count_file(entry.path(), self.context)
+    }
+}
+
+impl ParallelVisitor for Worker<'_> {
+    fn visit(&mut self, entry: Result<DirEntry, ignore::Error>) -> WalkState {
+        match entry.and_then(|entry| self.visit_entry(entry)) {
+            Ok(()) => WalkState::Continue,
+            // A symlink back to an ancestor; the directory is already being walked
+            Err(ignore::Error::WithDepth { err, .. })
+                if matches!(*err, ignore::Error::Loop { .. }) =>
+            {
+                WalkState::Continue
+            }
+            Err(e) if self.context.config.strict => {
+                self.error = Some(e);
+                WalkState::Quit
+            }
+            Err(e) => {
+                self.stats.errors.push(ScanError::new(&e));
+                WalkState::Continue
+            }
+        }
+    }
+}
+
+fn with_path(error: io::Error, path: &Path) -> ignore::Error {
+    ignore::Error::WithPath {
+        path: path.to_path_buf(),
+        err: Box::new(ignore::Error::Io(error)),
+    }
+}
+
+impl Drop for Worker<'_> {
+    fn drop(&mut self) {
+        self.context
+            .links
+            .counted
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .extend(self.counted_links.drain());
+        let result = match self.error.take() {
+            Some(e) => Err(e),
+            None => Ok(mem::take(&mut self.stats)),
+        };
+        self.results
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(result);
+    }
+}
+
+#[derive(Default)]
 struct LineStats {
     total: usize,
     code: usize,
     comments: usize,
+    doc_comments: usize,
     blank: usize,
+    mixed: usize,
+    /// Lines of `total` that are test code.
+    test: usize,
+}
+
+impl LineStats {
+    fn add(&mut self, other: &LineStats) {
+        self.total += other.total;
+        self.code += other.code;
+        self.comments += other.comments;
+        self.doc_comments += other.doc_comments;
+        self.blank += other.blank;
+        self.mixed += other.mixed;
+        self.test += other.test;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::test_support::scratch_dir;
+    use crate::types::{FallbackEncoding, OutputFormat};
+    use std::path::PathBuf;
+
+    fn config_with_threads(threads: usize) -> CountConfig {
+        CountConfig {
+            path: PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src"),
+            include_hidden: false,
+            output_format: OutputFormat::Json,
+            ignore: IgnorePolicy::default(),
+            include_blank_lines: false,
+            include_comments: false,
+            mixed_as: MixedPolicy::Code,
+            fallback_encoding: FallbackEncoding::Latin1,
+            threads,
+            languages: None,
+            mappings: Vec::new(),
+            verbose: false,
+            by_file: false,
+            sort: FileSort::Lines,
+            by_dir: false,
+            depth: 1,
+            strict: false,
+            follow_symlinks: false,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            only_languages: Vec::new(),
+            excluded_languages: Vec::new(),
+            exclude_generated: false,
+            vendored_patterns: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_totals_do_not_depend_on_thread_count() {
+        let single = config_with_threads(1);
+        let mut expected = ProjectStats::new();
+        expected.scan_directory(&single.path, &single).unwrap();
+        assert!(expected.total_files > 0);
+
+        for threads in [2, 4, 8] {
+            let config = config_with_threads(threads);
+            let mut stats = ProjectStats::new();
+            stats.scan_directory(&config.path, &config).unwrap();
+            assert_eq!(stats, expected, "threads = {}", threads);
+        }
+    }
+
+    #[test]
+    fn test_mixed_line_policies() {
+        let file_type = FileType::from_path(Path::new("lib.rs"));
+        let source = r"let x = 5; // explain
+// note
+let y = 6;
+";
+
+        for (policy, code, comments, total) in [
+            (MixedPolicy::Code, 2, 1, 2),
+            (MixedPolicy::Comment, 1, 2, 1),
+            (MixedPolicy::Both, 2, 2, 2),
+        ] {
+            let mut config = config_with_threads(1);
+            config.mixed_as = policy;
+
+            let stats = ProjectStats::new().analyze_lines(source, &file_type, &config);
+            assert_eq!(stats.mixed, 1, "{:?}", policy);
+            assert_eq!(
+                (stats.code, stats.comments, stats.total),
+                (code, comments, total),
+                "{:?}",
+                policy
+            );
+        }
+    }
+
+    #[test]
+    fn test_merge_adds_language_counts() {
+        let mut a = ProjectStats::new();
+        a.total_files = 1;
+        a.total_lines = 10;
+        a.file_types.insert(
+            "Rust".to_string(),
+            FileTypeStats {
+                count: 1,
+                lines: 10,
+                ..Default::default()
+            },
+        );
+
+        let mut b = ProjectStats::new();
+        b.total_files = 2;
+        b.total_lines = 5;
+        b.file_types.insert(
+            "Rust".to_string(),
+            FileTypeStats {
+                count: 1,
+                lines: 3,
+                ..Default::default()
+            },
+        );
+        b.file_types.insert(
+            "Python".to_string(),
+            FileTypeStats {
+                count: 1,
+                lines: 2,
+                ..Default::default()
+            },
+        );
+
+        a.merge(b);
+        assert_eq!(a.total_files, 3);
+        assert_eq!(a.total_lines, 15);
+        assert_eq!(a.file_types["Rust"].lines, 13);
+        assert_eq!(a.file_types["Rust"].count, 2);
+        assert_eq!(a.file_types["Python"].lines, 2);
+    }
+
+    #[test]
+    fn test_extension_mappings() {
+        let root = scratch_dir("mappings");
+        fs::write(
+            root.join("header.inc"),
+            r"<?php
+// shared
+echo 1;
+",
+        )
+        .unwrap();
+        fs::write(root.join("tool.h"), "class A {};\n").unwrap();
+        fs::write(root.join("fixture.dat"), "not counted\n").unwrap();
+        fs::write(root.join("model.blob"), "text, but mapped to binary\n").unwrap();
+
+        let mut config = config_with_threads(1);
+        config.path = root.clone();
+        config.mappings = ["inc=php", "h=C++", "dat=ignore", "blob=binary"]
+            .iter()
+            .map(|mapping| mapping.parse().unwrap())
+            .collect();
+
+        let mut stats = ProjectStats::new();
+        stats.scan_directory(&root, &config).unwrap();
+
+        assert_eq!(stats.total_files, 3);
+        assert_eq!(stats.file_types["PHP"].comment_lines, 1);
+        assert_eq!(stats.file_types["C++"].count, 1);
+        assert_eq!(stats.file_types["Binary"].count, 1);
+        assert_eq!(stats.file_types["Binary"].lines, 0);
+        assert!(!stats.file_types.contains_key("Text"));
+
+        config.mappings = vec!["inc=Klingon".parse().unwrap()];
+        let error = ProjectStats::new()
+            .scan_directory(&root, &config)
+            .unwrap_err();
+        assert!(error.to_string().contains("Klingon"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_embedded_regions_count_towards_their_language() {
+        let root = scratch_dir("embedded");
+        fs::write(
+            root.join("index.html"),
+            r"<p>hi</p>
+<script>
+// boot
+start();
+run();
+</script>
+",
+        )
+        .unwrap();
+        fs::write(
+            root.join("README.md"),
+            r"# Demo
+```py
+x = 1
+```
+",
+        )
+        .unwrap();
+
+        let mut config = config_with_threads(1);
+        config.path = root.clone();
+        let mut stats = ProjectStats::new();
+        stats.scan_directory(&root, &config).unwrap();
+
+        assert_eq!(stats.total_files, 2);
+        assert_eq!(stats.total_embedded_lines, 3);
+        assert_eq!(stats.file_types["HTML"].count, 1);
+        assert_eq!(stats.file_types["HTML"].lines, 3);
+        assert_eq!(stats.file_types["JavaScript"].count, 0);
+        assert_eq!(stats.file_types["JavaScript"].code_lines, 2);
+        assert_eq!(stats.file_types["JavaScript"].comment_lines, 1);
+        assert_eq!(stats.file_types["JavaScript"].embedded_lines, 2);
+        assert_eq!(stats.file_types["Python"].embedded_lines, 1);
+        assert_eq!(stats.file_types["Markdown"].lines, 3);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_unreadable_paths_are_collected() {
+        let root = scratch_dir("errors").join("missing");
+
+        let mut config = config_with_threads(2);
+        config.path = root.clone();
+        let mut stats = ProjectStats::new();
+        stats.scan_directory(&root, &config).unwrap();
+        assert_eq!(stats.total_files, 0);
+        assert_eq!(stats.errors.len(), 1);
+        assert_eq!(stats.errors[0].kind, "NotFound");
+        assert_eq!(
+            stats.errors[0].path.as_deref(),
+            Some(root.to_string_lossy().as_ref())
+        );
+
+        config.strict = true;
+        assert!(ProjectStats::new().scan_directory(&root, &config).is_err());
+
+        let error = ScanError::new(&with_path(
+            io::Error::from(io::ErrorKind::PermissionDenied),
+            Path::new("secret.rs"),
+        ));
+        assert_eq!(error.path.as_deref(), Some("secret.rs"));
+        assert_eq!(error.kind, "PermissionDenied");
+
+        fs::remove_dir_all(root.parent().unwrap()).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_links_are_counted_once() {
+        use std::os::unix::fs::symlink;
+
+        let root = scratch_dir("links");
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/lib.rs"), "fn a() {}\n").unwrap();
+        fs::hard_link(root.join("src/lib.rs"), root.join("src/copy.rs")).unwrap();
+        symlink(root.join("src"), root.join("linked")).unwrap();
+        symlink(&root, root.join("src/back")).unwrap();
+
+        let mut config = config_with_threads(4);
+        config.path = root.clone();
+        let mut stats = ProjectStats::new();
+        stats.scan_directory(&root, &config).unwrap();
+        assert_eq!(stats.total_files, 1);
+        assert_eq!(stats.total_duplicate_files, 1);
+
+        config.follow_symlinks = true;
+        let mut stats = ProjectStats::new();
+        stats.scan_directory(&root, &config).unwrap();
+        assert_eq!(stats.total_files, 1);
+        assert_eq!(stats.total_lines, 1);
+        assert_eq!(stats.total_duplicate_files, 3);
+        assert!(stats.errors.is_empty(), "{:?}", stats.errors);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hard_links_count_through_smallest_path() {
+        let root = scratch_dir("hard-links");
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("vendor")).unwrap();
+        fs::write(root.join("vendor/util.rs"), "fn a() {}\n").unwrap();
+        fs::hard_link(root.join("vendor/util.rs"), root.join("src/util.rs")).unwrap();
+
+        for threads in [1, 2, 8] {
+            let mut config = config_with_threads(threads);
+            config.path = root.clone();
+            config.by_dir = true;
+            let mut stats = ProjectStats::new();
+            stats.scan_directory(&root, &config).unwrap();
+
+            assert_eq!(stats.total_files, 1, "threads = {}", threads);
+            assert_eq!(stats.total_duplicate_files, 1);
+            assert_eq!(stats.vendored.total_files, 0);
+            let directories = stats.directories.unwrap();
+            assert_eq!(directories.directories["src"].file_types["Rust"].lines, 1);
+        }
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_following_links_defers_only_linked_paths() {
+        use std::os::unix::fs::symlink;
+
+        let root = scratch_dir("follow-plain");
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/a.rs"), "fn a() {}\n").unwrap();
+        fs::write(root.join("src/b.rs"), "fn b() {}\n").unwrap();
+        fs::write(root.join("main.rs"), "fn main() {}\n").unwrap();
+
+        let claims = |root: &Path| {
+            let links = LinkTracker::new(true);
+            let mut claims = BTreeMap::new();
+            for entry in WalkBuilder::new(root).follow_links(true).build() {
+                let entry = entry.unwrap();
+                if entry.file_type().unwrap().is_dir() {
+                    links.visit_dir(&entry);
+                } else {
+                    let path = entry.path().strip_prefix(root).unwrap().to_path_buf();
+                    claims.insert(path, links.claim(&entry).unwrap());
+                }
+            }
+            claims
+        };
+
+        // Plain files are counted by the workers, not after the walk
+        let plain = claims(&root);
+        assert_eq!(plain.len(), 3);
+        assert!(plain
+            .values()
+            .all(|claim| matches!(claim, Claim::Counted(_))));
+
+        symlink(root.join("src"), root.join("linked")).unwrap();
+        symlink(root.join("main.rs"), root.join("entry.rs")).unwrap();
+        let linked = claims(&root);
+        assert!(matches!(linked[Path::new("src/a.rs")], Claim::Counted(_)));
+        assert_eq!(linked[Path::new("linked/a.rs")], Claim::Deferred);
+        assert_eq!(linked[Path::new("entry.rs")], Claim::Deferred);
+
+        let mut config = config_with_threads(4);
+        config.path = root.clone();
+        config.follow_symlinks = true;
+        let mut stats = ProjectStats::new();
+        stats.scan_directory(&root, &config).unwrap();
+        assert_eq!(stats.total_files, 3);
+        assert_eq!(stats.total_duplicate_files, 3);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_path_and_language_filters() {
+        let root = scratch_dir("filters");
+        fs::create_dir_all(root.join("src/generated")).unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(root.join("src/util.py"), "x = 1\n").unwrap();
+        fs::write(root.join("src/data.json"), "{}\n").unwrap();
+        fs::write(root.join("src/generated/api.rs"), "fn api() {}\n").unwrap();
+        fs::write(root.join("notes.md"), "# Notes\n").unwrap();
+
+        let mut config = config_with_threads(2);
+        config.path = root.clone();
+        config.include = vec!["src/**".to_string()];
+        config.exclude = vec!["**/generated/**".to_string()];
+        config.excluded_languages = vec!["json".to_string()];
+        let mut stats = ProjectStats::new();
+        stats.scan_directory(&root, &config).unwrap();
+
+        assert_eq!(stats.total_files, 2);
+        assert_eq!(stats.total_size_bytes, 19);
+        assert_eq!(stats.file_types["Rust"].lines, 1);
+        assert!(stats.file_types.contains_key("Python"));
+        assert!(!stats.file_types.contains_key("JSON"));
+        assert!(!stats.file_types.contains_key("Markdown"));
+        assert_eq!(stats.filters.excluded_languages, vec!["JSON"]);
+
+        config.only_languages = vec!["Rust".to_string()];
+        let mut stats = ProjectStats::new();
+        stats.scan_directory(&root, &config).unwrap();
+        assert_eq!(stats.total_files, 1);
+
+        fs::write(root.join("src/todo.txt"), "later\n").unwrap();
+        config.only_languages.clear();
+        config.excluded_languages = vec!["text".to_string()];
+        let mut stats = ProjectStats::new();
+        stats.scan_directory(&root, &config).unwrap();
+        assert_eq!(stats.total_files, 3);
+        assert!(!stats.file_types.contains_key("Text"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_language_filters_apply_to_embedded_regions() {
+        let root = scratch_dir("filters-embedded");
+        fs::write(
+            root.join("index.html"),
+            r"<p>hi</p>
+<script>
+// boot
+start();
+</script>
+",
+        )
+        .unwrap();
+        fs::write(root.join("tool.h"), "class A {};\n").unwrap();
+
+        let mut config = config_with_threads(1);
+        config.path = root.clone();
+        config.excluded_languages = vec!["JavaScript".to_string()];
+        let mut stats = ProjectStats::new();
+        stats.scan_directory(&root, &config).unwrap();
+        assert_eq!(stats.total_files, 2);
+        assert_eq!(stats.file_types["HTML"].count, 1);
+        assert!(!stats.file_types.contains_key("JavaScript"));
+        assert_eq!(stats.total_embedded_lines, 0);
+
+        // The script still counts when its host page is left out
+        config.excluded_languages.clear();
+        config.only_languages = vec!["JavaScript".to_string()];
+        let mut stats = ProjectStats::new();
+        stats.scan_directory(&root, &config).unwrap();
+        assert_eq!(stats.total_files, 0);
+        assert!(!stats.file_types.contains_key("HTML"));
+        assert_eq!(stats.file_types["JavaScript"].code_lines, 1);
+        assert_eq!(stats.file_types["JavaScript"].comment_lines, 1);
+        assert_eq!(stats.file_types["JavaScript"].embedded_lines, 1);
+        assert_eq!(stats.total_lines, 1);
+
+        // Content rules may still turn a C header into C++
+        config.only_languages = vec!["C++".to_string()];
+        let mut stats = ProjectStats::new();
+        stats.scan_directory(&root, &config).unwrap();
+        assert_eq!(stats.total_files, 1);
+        assert_eq!(stats.file_types["C++"].count, 1);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_tallyhawkignore() {
+        let root = scratch_dir("tallyhawkignore");
+        fs::create_dir_all(root.join("tests/fixtures")).unwrap();
+        fs::write(root.join("lib.rs"), "fn a() {}\n").unwrap();
+        fs::write(root.join("tests/fixtures/big.rs"), "fn b() {}\n").unwrap();
+        fs::write(root.join("tests/snap.snap"), "output\n").unwrap();
+        fs::write(root.join(TALLYHAWK_IGNORE), "*.snap\n").unwrap();
+        fs::write(root.join("tests").join(TALLYHAWK_IGNORE), "fixtures/\n").unwrap();
+
+        let mut config = config_with_threads(1);
+        config.path = root.clone();
+        let mut stats = ProjectStats::new();
+        stats.scan_directory(&root, &config).unwrap();
+        assert_eq!(stats.total_files, 1);
+
+        config.ignore.tallyhawkignore = false;
+        let mut stats = ProjectStats::new();
+        stats.scan_directory(&root, &config).unwrap();
+        assert_eq!(stats.total_files, 3);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_ignore_policy() {
+        let root = scratch_dir("ignore-policy");
+        let project = root.join("project");
+        fs::create_dir_all(project.join(".git")).unwrap();
+        fs::write(root.join(".ignore"), "*.bak\n").unwrap();
+        fs::write(project.join(".gitignore"), "*.log\n").unwrap();
+        fs::write(project.join(".ignore"), "*.tmp\n").unwrap();
+        fs::write(project.join("main.rs"), "fn main() {}\n").unwrap();
+        fs::write(project.join("debug.log"), "log\n").unwrap();
+        fs::write(project.join("scratch.tmp"), "tmp\n").unwrap();
+        fs::write(project.join("old.bak"), "bak\n").unwrap();
+
+        let count = |ignore: IgnorePolicy| {
+            let mut config = config_with_threads(1);
+            config.path = project.clone();
+            config.ignore = ignore;
+            let mut stats = ProjectStats::new();
+            stats.scan_directory(&project, &config).unwrap();
+            assert_eq!(stats.ignore_policy, ignore);
+            stats.total_files
+        };
+
+        let all = IgnorePolicy::default();
+        assert_eq!(count(all), 1);
+        assert_eq!(
+            count(IgnorePolicy {
+                gitignore: false,
+                ..all
+            }),
+            2
+        );
+        assert_eq!(
+            count(IgnorePolicy {
+                ignore_files: false,
+                ..all
+            }),
+            3
+        );
+        assert_eq!(
+            count(IgnorePolicy {
+                parents: false,
+                ..all
+            }),
+            2
+        );
+        assert_eq!(count(IgnorePolicy::none()), 4);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_generated_files() {
+        let root = scratch_dir("generated");
+        fs::write(
+            root.join("main.go"),
+            r"package main
+
+func main() {}
+",
+        )
+        .unwrap();
+        fs::write(
+            root.join("api.go"),
+            r"// Code generated by protoc-gen-go. DO NOT EDIT.
+package api
+",
+        )
+        .unwrap();
+        fs::write(root.join("app.min.js"), "var a=1;\n").unwrap();
+        fs::write(root.join("schema.go"), "package schema\n").unwrap();
+        fs::write(root.join("yarn.lock"), "# @generated\n").unwrap();
+        fs::write(
+            root.join(".gitattributes"),
+            r"schema.go linguist-generated
+yarn.lock -linguist-generated
+",
+        )
+        .unwrap();
+
+        let mut config = config_with_threads(1);
+        config.path = root.clone();
+        config.by_file = true;
+        let mut stats = ProjectStats::new();
+        stats.scan_directory(&root, &config).unwrap();
+
+        assert_eq!(stats.total_generated_files, 3);
+        assert_eq!(stats.file_types["Go"].count, 3);
+        assert_eq!(stats.file_types["Go"].generated_files, 2);
+        assert_eq!(stats.file_types["Go"].generated_lines, 2);
+        assert_eq!(stats.file_types["JavaScript"].generated_lines, 1);
+        assert_eq!(stats.total_generated_lines, 3);
+        let generated: Vec<_> = stats
+            .files
+            .iter()
+            .filter(|file| file.generated)
+            .map(|file| Path::new(&file.path).file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(generated, vec!["api.go", "app.min.js", "schema.go"]);
+
+        config.exclude_generated = true;
+        let mut stats = ProjectStats::new();
+        stats.scan_directory(&root, &config).unwrap();
+        assert_eq!(stats.total_generated_files, 0);
+        assert_eq!(stats.file_types["Go"].count, 1);
+        assert!(!stats.file_types.contains_key("JavaScript"));
+        assert_eq!(stats.total_files, 2);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_vendored_code_is_counted_apart() {
+        let root = scratch_dir("vendored");
+        for dir in [
+            "src",
+            "vendor/lib",
+            "deps/zlib",
+            "third_party/ours",
+            "static",
+        ] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(
+            root.join("vendor/lib/a.rs"),
+            r"fn a() {}
+fn b() {}
+",
+        )
+        .unwrap();
+        fs::write(root.join("deps/zlib/z.c"), "int z;\n").unwrap();
+        fs::write(root.join("third_party/ours/o.rs"), "fn o() {}\n").unwrap();
+        fs::write(root.join("src/shim.rs"), "fn shim() {}\n").unwrap();
+        fs::write(root.join("static/jquery.js"), "var $ = 1;\n").unwrap();
+        fs::write(root.join("static/site.css"), "p {}\n").unwrap();
+        fs::write(
+            root.join(".gitattributes"),
+            r"third_party/ours/** -linguist-vendored
+src/shim.rs linguist-vendored
+",
+        )
+        .unwrap();
+
+        let mut config = config_with_threads(2);
+        config.path = root.clone();
+        config.by_file = true;
+        config.vendored_patterns = vec!["deps/".to_string(), "static/*.js".to_string()];
+        let mut stats = ProjectStats::new();
+        stats.scan_directory(&root, &config).unwrap();
+
+        assert_eq!(stats.total_files, 3);
+        assert_eq!(stats.file_types["Rust"].lines, 2);
+        assert_eq!(stats.file_types["CSS"].count, 1);
+        assert!(!stats.file_types.contains_key("C"));
+        assert!(!stats.file_types.contains_key("JavaScript"));
+
+        assert_eq!(stats.vendored.total_files, 4);
+        assert_eq!(stats.vendored.total_lines, 5);
+        assert_eq!(stats.vendored.file_types["Rust"].count, 2);
+        assert_eq!(stats.vendored.file_types["C"].lines, 1);
+        assert_eq!(stats.vendored.file_types["JavaScript"].count, 1);
+        assert!(stats.vendored.patterns.contains(&"deps/".to_string()));
+        assert_eq!(stats.files.iter().filter(|file| file.vendored).count(), 4);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_test_and_production_lines() {
+        let root = scratch_dir("test-split");
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("tests")).unwrap();
+        fs::write(
+            root.join("src/lib.rs"),
+            r"pub fn add() {}
+
+#[cfg(test)]
+mod tests {
+    // checks
+    #[test]
+    fn it() {}
+}
+",
+        )
+        .unwrap();
+        fs::write(
+            root.join("tests/it.rs"),
+            r"fn it() {}
+fn more() {}
+",
+        )
+        .unwrap();
+        fs::write(root.join("src/api_test.go"), "package api\n").unwrap();
+        fs::write(root.join("src/api.go"), "package api\n").unwrap();
+
+        let mut config = config_with_threads(2);
+        config.path = root.clone();
+        let mut stats = ProjectStats::new();
+        stats.scan_directory(&root, &config).unwrap();
+
+        let rust = &stats.file_types["Rust"];
+        assert_eq!(rust.lines, 8);
+        assert_eq!(rust.test_lines, 7);
+        let go = &stats.file_types["Go"];
+        assert_eq!((go.lines, go.test_lines), (2, 1));
+        assert_eq!(stats.total_test_lines, 8);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_directory_rollups() {
+        let root = scratch_dir("by-dir");
+        fs::create_dir_all(root.join("crates/core/src")).unwrap();
+        fs::create_dir_all(root.join("crates/cli")).unwrap();
+        fs::write(
+            root.join("crates/core/src/lib.rs"),
+            r"fn a() {}
+fn b() {}
+",
+        )
+        .unwrap();
+        fs::write(root.join("crates/cli/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(
+            root.join("crates/cli/Cargo.toml"),
+            r#"[package]
+name = "cli"
+"#,
+        )
+        .unwrap();
+
+        let mut config = config_with_threads(4);
+        config.path = root.clone();
+        config.by_dir = true;
+        config.depth = 2;
+        let mut stats = ProjectStats::new();
+        stats.scan_directory(&root, &config).unwrap();
+
+        let directories = stats.directories.as_ref().unwrap();
+        assert_eq!(directories.totals().lines, stats.total_lines);
+        let crates = &directories.directories["crates"];
+        assert_eq!(crates.file_types["Rust"].lines, 3);
+        assert_eq!(crates.directories["core"].file_types["Rust"].lines, 2);
+        assert_eq!(crates.directories["cli"].file_types["Rust"].lines, 1);
+        assert_eq!(crates.directories["cli"].file_types["TOML"].count, 1);
+        assert!(crates.directories["core"].directories.is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_per_file_records() {
+        let root = scratch_dir("by-file");
+        fs::write(
+            root.join("main.rs"),
+            r"// entry
+fn main() {}
+
+fn run() {} // todo
+",
+        )
+        .unwrap();
+        fs::write(
+            root.join("index.html"),
+            r"<p>hi</p>
+<script>
+start();
+</script>
+",
+        )
+        .unwrap();
+        fs::write(root.join("logo.png"), [0x89, b'P', b'N', b'G']).unwrap();
+
+        let mut config = config_with_threads(4);
+        config.path = root.clone();
+        let mut stats = ProjectStats::new();
+        stats.scan_directory(&root, &config).unwrap();
+        assert!(stats.files.is_empty());
+
+        config.by_file = true;
+        config.include_blank_lines = true;
+        let mut stats = ProjectStats::new();
+        stats.scan_directory(&root, &config).unwrap();
+
+        let names: Vec<_> = stats
+            .files
+            .iter()
+            .map(|file| Path::new(&file.path).file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["index.html", "logo.png", "main.rs"]);
+
+        let html = &stats.files[0];
+        assert_eq!(html.language, "HTML");
+        assert_eq!((html.lines, html.code_lines), (4, 4));
+        let main = &stats.files[2];
+        assert_eq!(main.language, "Rust");
+        assert_eq!(
+            (
+                main.lines,
+                main.code_lines,
+                main.comment_lines,
+                main.blank_lines,
+                main.mixed_lines
+            ),
+            (3, 2, 1, 1, 1)
+        );
+        assert_eq!(main.size_bytes, 43);
+
+        stats.sort_files(FileSort::Code);
+        assert_eq!(stats.files[0].language, "HTML");
+        stats.sort_files(FileSort::Size);
+        assert_eq!(stats.files[2].language, "Image");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
 }
@@ -0,0 +1,117 @@
+use crate::stats::counter::FileTypeStats;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Component, Path};
+
+/// Per-language counts for one directory, including everything below it.
+/// Subdirectories are kept down to the `--depth` the scan was run with;
+/// files deeper than that are rolled up into their ancestor at that depth.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DirectoryStats {
+    pub file_types: BTreeMap<String, FileTypeStats>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub directories: BTreeMap<String, DirectoryStats>,
+}
+
+impl DirectoryStats {
+    /// Add the counts of one file, given by its path relative to the scan root,
+    /// to this directory and to each of its directories down to `depth` levels.
+    pub fn add_file(
+        &mut self,
+        relative_path: &Path,
+        depth: usize,
+        file_types: &HashMap<String, FileTypeStats>,
+    ) {
+        let directories = relative_path
+            .parent()
+            .into_iter()
+            .flat_map(Path::components)
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .take(depth);
+
+        let mut node = self;
+        node.add_counts(file_types);
+        for name in directories {
+            node = node.directories.entry(name).or_default();
+            node.add_counts(file_types);
+        }
+    }
+
+    fn add_counts(&mut self, file_types: &HashMap<String, FileTypeStats>) {
+        for (language, file_stats) in file_types {
+            self.file_types
+                .entry(language.clone())
+                .or_default()
+                .merge(file_stats.clone());
+        }
+    }
+
+    /// Fold another rollup of the same scan into this one.
+    pub fn merge(&mut self, other: DirectoryStats) {
+        for (language, file_stats) in other.file_types {
+            self.file_types
+                .entry(language)
+                .or_default()
+                .merge(file_stats);
+        }
+        for (name, directory) in other.directories {
+            self.directories.entry(name).or_default().merge(directory);
+        }
+    }
+
+    /// Counts of all languages in this directory added together.
+    pub fn totals(&self) -> FileTypeStats {
+        let mut totals = FileTypeStats::default();
+        for file_stats in self.file_types.values() {
+            totals.merge(file_stats.clone());
+        }
+        totals
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rust_file(lines: usize) -> HashMap<String, FileTypeStats> {
+        let mut file_types = HashMap::new();
+        file_types.insert(
+            "Rust".to_string(),
+            FileTypeStats {
+                count: 1,
+                lines,
+                ..Default::default()
+            },
+        );
+        file_types
+    }
+
+    #[test]
+    fn test_rollup_depth() {
+        let mut root = DirectoryStats::default();
+        root.add_file(Path::new("build.rs"), 1, &rust_file(5));
+        root.add_file(Path::new("crates/core/src/lib.rs"), 2, &rust_file(10));
+        root.add_file(Path::new("crates/cli/main.rs"), 2, &rust_file(3));
+
+        assert_eq!(root.totals().lines, 18);
+        assert_eq!(root.totals().count, 3);
+
+        let crates = &root.directories["crates"];
+        assert_eq!(crates.file_types["Rust"].lines, 13);
+        assert_eq!(crates.directories["core"].file_types["Rust"].lines, 10);
+        assert_eq!(crates.directories["cli"].file_types["Rust"].lines, 3);
+        assert!(crates.directories["core"].directories.is_empty());
+
+        let mut other = DirectoryStats::default();
+        other.add_file(Path::new("crates/cli/args.rs"), 2, &rust_file(4));
+        root.merge(other);
+        assert_eq!(
+            root.directories["crates"].directories["cli"].totals().lines,
+            7
+        );
+        assert_eq!(root.totals().lines, 22);
+    }
+}
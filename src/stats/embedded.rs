@@ -0,0 +1,253 @@
+use crate::stats::file_types::FileType;
+use crate::stats::registry::LanguageRegistry;
+use serde::Deserialize;
+
+/// How a host language embeds code in other languages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Embedding {
+    /// `<script>` and `<style>` elements, as in HTML, Vue and Svelte files.
+    Html,
+    /// Fenced code blocks with a language info string.
+    Markdown,
+    /// Jupyter notebook cells; see `notebook::split`.
+    Notebook,
+}
+
+/// Lines of a file attributed to one language.
+#[derive(Debug)]
+pub struct Region {
+    pub file_type: FileType,
+    pub content: String,
+    /// Whether the lines came from inside another language's file.
+    pub embedded: bool,
+}
+
+/// Split a host file into its own lines and the regions written in embedded
+/// languages. The host region comes first and keeps the tag and fence lines;
+/// each embedded block becomes its own region so its comments are followed
+/// with that language's rules.
+pub fn split(
+    content: &str,
+    host: &FileType,
+    embedding: Embedding,
+    registry: &'static LanguageRegistry,
+) -> Vec<Region> {
+    let mut host_lines = Vec::new();
+    let mut regions = Vec::new();
+    // The open block, if any; blocks in no known language belong to the host
+    let mut open: Option<(Closer, Option<FileType>, Vec<&str>)> = None;
+
+    for line in content.lines() {
+        if let Some((closer, file_type, lines)) = &mut open {
+            if closer.closes(line) {
+                if let Some(file_type) = file_type.take() {
+                    regions.push(region(file_type, lines));
+                }
+                open = None;
+                host_lines.push(line);
+            } else if file_type.is_some() {
+                lines.push(line);
+            } else {
+                host_lines.push(line);
+            }
+            continue;
+        }
+
+        host_lines.push(line);
+        let opened = match embedding {
+            Embedding::Html => html_block(line, registry),
+            Embedding::Markdown => fence(line, registry),
+            // Notebooks are JSON and are split by cell, not line by line
+            Embedding::Notebook => None,
+        };
+        if let Some((closer, file_type)) = opened {
+            open = Some((closer, file_type, Vec::new()));
+        }
+    }
+
+    // An unterminated block runs to the end of the file
+    if let Some((_, Some(file_type), lines)) = open {
+        regions.push(region(file_type, &lines));
+    }
+
+    let mut all = vec![Region {
+        file_type: host.clone(),
+        content: host_lines.join("\n"),
+        embedded: false,
+    }];
+    all.extend(
+        regions
+            .into_iter()
+            .filter(|region| !region.content.is_empty()),
+    );
+    all
+}
+
+fn region(file_type: FileType, lines: &[&str]) -> Region {
+    Region {
+        file_type,
+        content: lines.join("\n"),
+        embedded: true,
+    }
+}
+
+/// What ends an embedded block.
+#[derive(Debug)]
+enum Closer {
+    /// A closing tag such as `</script`, matched case-insensitively.
+    Tag(&'static str),
+    /// A fence of at least this many of the same character.
+    Fence(char, usize),
+}
+
+impl Closer {
+    fn closes(&self, line: &str) -> bool {
+        match *self {
+            Closer::Tag(tag) => line.to_ascii_lowercase().contains(tag),
+            Closer::Fence(c, len) => {
+                let trimmed = line.trim();
+                trimmed.len() >= len && trimmed.chars().all(|t| t == c)
+            }
+        }
+    }
+}
+
+/// A `<script>` or `<style>` element whose content starts on the next line,
+/// with its language. Elements that close on the same line are ignored;
+/// scripts holding templates or unknown languages stay with the host.
+fn html_block(
+    line: &str,
+    registry: &'static LanguageRegistry,
+) -> Option<(Closer, Option<FileType>)> {
+    let lower = line.to_ascii_lowercase();
+
+    let (tag, closer, default) = if lower.contains("<script") {
+        ("<script", "</script", "JavaScript")
+    } else if lower.contains("<style") {
+        ("<style", "</style", "CSS")
+    } else {
+        return None;
+    };
+
+    let start = lower.find(tag)?;
+    let end = start + lower[start..].find('>')?;
+    if lower[end..].contains(closer) {
+        return None;
+    }
+
+    let attributes = &lower[start + tag.len()..end];
+    let language = match (attribute(attributes, "lang"), attribute(attributes, "type")) {
+        (Some(lang), _) => registry.lookup_name(lang),
+        (None, Some(kind)) if kind.contains("typescript") => registry.lookup_name("typescript"),
+        (None, Some(kind)) if kind.contains("json") => registry.lookup_name("json"),
+        (None, Some(kind))
+            if !(kind.contains("javascript") || kind == "module" || kind == "text/css") =>
+        {
+            None
+        }
+        _ => registry.lookup_name(default),
+    };
+
+    Some((Closer::Tag(closer), language))
+}
+
+/// Value of an HTML attribute in the text between a tag name and its `>`.
+fn attribute<'a>(attributes: &'a str, name: &str) -> Option<&'a str> {
+    let pattern = format!("{}=", name);
+    let start = attributes
+        .match_indices(&pattern)
+        .find(|(i, _)| *i == 0 || attributes[..*i].ends_with(char::is_whitespace))
+        .map(|(i, _)| i + pattern.len())?;
+
+    let value = &attributes[start..];
+    match value.chars().next()? {
+        quote @ ('"' | '\'') => value[1..].split(quote).next(),
+        _ => value.split(|c: char| c.is_whitespace() || c == '/').next(),
+    }
+}
+
+/// A Markdown code fence (```` ``` ```` or `~~~`) with the language its info
+/// string names. Fences without a recognised language stay with the host.
+fn fence(line: &str, registry: &'static LanguageRegistry) -> Option<(Closer, Option<FileType>)> {
+    let trimmed = line.trim_start();
+    let c = trimmed.chars().next().filter(|&c| c == '`' || c == '~')?;
+    let len = trimmed.chars().take_while(|&t| t == c).count();
+    if len < 3 {
+        return None;
+    }
+
+    let language = trimmed[len..]
+        .trim()
+        .trim_start_matches(['{', '.'])
+        .split(|c: char| c.is_whitespace() || c == ',' || c == '}')
+        .next()
+        .filter(|info| !info.is_empty())
+        .and_then(|info| registry.lookup_name(info));
+
+    Some((Closer::Fence(c, len), language))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn regions(file_name: &str, content: &str) -> Vec<(String, String, bool)> {
+        let host = FileType::from_path(Path::new(file_name));
+        let embedding = host.embeds.expect("host embeds other languages");
+        split(content, &host, embedding, LanguageRegistry::builtin())
+            .into_iter()
+            .map(|region| (region.file_type.language, region.content, region.embedded))
+            .collect()
+    }
+
+    #[test]
+    fn test_html_script_and_style() {
+        let html = "<html>\n<style>\nbody { margin: 0; }\n</style>\n<script src=\"a.js\"></script>\n<script type=\"module\">\n// boot\nstart();\n</script>\n</html>";
+        assert_eq!(
+            regions("index.html", html),
+            vec![
+                (
+                    "HTML".to_string(),
+                    "<html>\n<style>\n</style>\n<script src=\"a.js\"></script>\n<script type=\"module\">\n</script>\n</html>".to_string(),
+                    false
+                ),
+                ("CSS".to_string(), "body { margin: 0; }".to_string(), true),
+                ("JavaScript".to_string(), "// boot\nstart();".to_string(), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_vue_lang_attributes() {
+        let vue = "<template>\n  <p>{{ x }}</p>\n</template>\n<script lang=\"ts\">\nexport default {}\n</script>\n<style lang='scss' scoped>\n.a { .b { color: red; } }\n</style>";
+        let languages: Vec<_> = regions("App.vue", vue)
+            .into_iter()
+            .map(|(language, _, _)| language)
+            .collect();
+        assert_eq!(languages, vec!["Vue", "TypeScript", "Sass"]);
+
+        let template = "<script type=\"text/x-template\">\n<p>hi</p>\n</script>";
+        assert_eq!(regions("page.html", template).len(), 1);
+    }
+
+    #[test]
+    fn test_markdown_fences() {
+        let markdown = "# Title\n```rust\nfn main() {}\n```\n````\n```py\n````\n~~~py\n# note\n~~~\n```js,ignore\nx()\n```";
+        assert_eq!(
+            regions("README.md", markdown),
+            vec![
+                (
+                    "Markdown".to_string(),
+                    "# Title\n```rust\n```\n````\n```py\n````\n~~~py\n~~~\n```js,ignore\n```"
+                        .to_string(),
+                    false
+                ),
+                ("Rust".to_string(), "fn main() {}".to_string(), true),
+                ("Python".to_string(), "# note".to_string(), true),
+                ("JavaScript".to_string(), "x()".to_string(), true),
+            ]
+        );
+    }
+}
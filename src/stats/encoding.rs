@@ -0,0 +1,198 @@
+use crate::types::FallbackEncoding;
+
+/// Text encoding a file was decoded from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+    Windows1252,
+}
+
+impl Encoding {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf8Bom => "UTF-8 BOM",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Latin1 => "ISO-8859-1",
+            Encoding::Windows1252 => "Windows-1252",
+        }
+    }
+
+    /// Whether decoding needed more than stripping a BOM.
+    pub fn is_transcoded(&self) -> bool {
+        !matches!(self, Encoding::Utf8 | Encoding::Utf8Bom)
+    }
+}
+
+impl From<FallbackEncoding> for Encoding {
+    fn from(fallback: FallbackEncoding) -> Self {
+        match fallback {
+            FallbackEncoding::Latin1 => Encoding::Latin1,
+            FallbackEncoding::Windows1252 => Encoding::Windows1252,
+        }
+    }
+}
+
+/// Detect an encoding from the first bytes of a file by BOM or UTF-16 shape.
+/// Returns `None` when the head gives no hint; the caller then tries UTF-8.
+/// UTF-16 text is full of NUL bytes, so this must run before binary sniffing.
+pub fn sniff(head: &[u8]) -> Option<Encoding> {
+    if head.starts_with(b"\xef\xbb\xbf") {
+        return Some(Encoding::Utf8Bom);
+    }
+    if head.starts_with(b"\xff\xfe") {
+        return Some(Encoding::Utf16Le);
+    }
+    if head.starts_with(b"\xfe\xff") {
+        return Some(Encoding::Utf16Be);
+    }
+
+    // Without a BOM, UTF-16 text written in mostly ASCII has a NUL in every
+    // other byte, all on the same side of each code unit.
+    let pairs = head.len() / 2;
+    if pairs < 2 {
+        return None;
+    }
+    let (even, odd) = head.chunks_exact(2).fold((0, 0), |(even, odd), pair| {
+        (
+            even + (pair[0] == 0) as usize,
+            odd + (pair[1] == 0) as usize,
+        )
+    });
+
+    if odd * 10 >= pairs * 7 && even == 0 {
+        Some(Encoding::Utf16Le)
+    } else if even * 10 >= pairs * 7 && odd == 0 {
+        Some(Encoding::Utf16Be)
+    } else {
+        None
+    }
+}
+
+/// Decode file content, lossily where needed.
+/// A sniffed encoding wins; otherwise valid UTF-8 is kept as is and anything
+/// else is decoded with the fallback codepage.
+pub fn decode(
+    bytes: Vec<u8>,
+    sniffed: Option<Encoding>,
+    fallback: FallbackEncoding,
+) -> (String, Encoding) {
+    match sniffed {
+        Some(Encoding::Utf8Bom) => {
+            let text = String::from_utf8_lossy(&bytes[3..]).into_owned();
+            (text, Encoding::Utf8Bom)
+        }
+        Some(encoding @ (Encoding::Utf16Le | Encoding::Utf16Be)) => {
+            (decode_utf16(&bytes, encoding), encoding)
+        }
+        _ => match String::from_utf8(bytes) {
+            Ok(text) => (text, Encoding::Utf8),
+            Err(e) => {
+                let encoding = Encoding::from(fallback);
+                (decode_single_byte(e.as_bytes(), encoding), encoding)
+            }
+        },
+    }
+}
+
+fn decode_utf16(bytes: &[u8], encoding: Encoding) -> String {
+    let bom = if bytes.starts_with(b"\xff\xfe") || bytes.starts_with(b"\xfe\xff") {
+        2
+    } else {
+        0
+    };
+
+    let units: Vec<u16> = bytes[bom..]
+        .chunks_exact(2)
+        .map(|pair| match encoding {
+            Encoding::Utf16Be => u16::from_be_bytes([pair[0], pair[1]]),
+            _ => u16::from_le_bytes([pair[0], pair[1]]),
+        })
+        .collect();
+
+    String::from_utf16_lossy(&units)
+}
+
+fn decode_single_byte(bytes: &[u8], encoding: Encoding) -> String {
+    bytes
+        .iter()
+        .map(|&b| match (encoding, b) {
+            (Encoding::Windows1252, 0x80..=0x9f) => WINDOWS_1252_HIGH[(b - 0x80) as usize],
+            _ => b as char,
+        })
+        .collect()
+}
+
+/// Windows-1252 characters for bytes 0x80..=0x9F; unassigned bytes map to
+/// the matching C1 control, as browsers do.
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '\u{20ac}', '\u{81}', '\u{201a}', '\u{192}', '\u{201e}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2c6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8d}', '\u{17d}', '\u{8f}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201c}', '\u{201d}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2dc}', '\u{2122}', '\u{161}', '\u{203a}', '\u{153}', '\u{9d}', '\u{17e}', '\u{178}',
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16(text: &str, big_endian: bool) -> Vec<u8> {
+        text.encode_utf16()
+            .flat_map(|unit| {
+                if big_endian {
+                    unit.to_be_bytes()
+                } else {
+                    unit.to_le_bytes()
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_bom_sniffing() {
+        assert_eq!(sniff(b"\xef\xbb\xbfx"), Some(Encoding::Utf8Bom));
+        assert_eq!(sniff(b"\xff\xfex\0"), Some(Encoding::Utf16Le));
+        assert_eq!(sniff(b"\xfe\xff\0x"), Some(Encoding::Utf16Be));
+        assert_eq!(sniff(b"plain ascii"), None);
+    }
+
+    #[test]
+    fn test_utf16_without_bom() {
+        let le = utf16("using System;\r\n", false);
+        let be = utf16("using System;\r\n", true);
+        assert_eq!(sniff(&le), Some(Encoding::Utf16Le));
+        assert_eq!(sniff(&be), Some(Encoding::Utf16Be));
+
+        let (text, encoding) = decode(le, Some(Encoding::Utf16Le), FallbackEncoding::Latin1);
+        assert_eq!(text, "using System;\r\n");
+        assert_eq!(encoding, Encoding::Utf16Le);
+    }
+
+    #[test]
+    fn test_decode_bom_and_fallbacks() {
+        let (text, encoding) = decode(
+            b"\xef\xbb\xbfint x;".to_vec(),
+            Some(Encoding::Utf8Bom),
+            FallbackEncoding::Latin1,
+        );
+        assert_eq!((text.as_str(), encoding), ("int x;", Encoding::Utf8Bom));
+
+        let (text, encoding) = decode(b"/* caf\xe9 */".to_vec(), None, FallbackEncoding::Latin1);
+        assert_eq!(
+            (text.as_str(), encoding),
+            ("/* caf\u{e9} */", Encoding::Latin1)
+        );
+
+        let (text, encoding) = decode(b"\x93q\x94".to_vec(), None, FallbackEncoding::Windows1252);
+        assert_eq!(
+            (text.as_str(), encoding),
+            ("\u{201c}q\u{201d}", Encoding::Windows1252)
+        );
+        assert!(encoding.is_transcoded());
+    }
+}
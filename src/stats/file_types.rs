@@ -1,221 +1,137 @@
+use crate::stats::embedded::Embedding;
+use crate::stats::registry::{LanguageDef, LanguageRegistry};
 use std::path::Path;
 
 #[derive(Debug, Clone)]
 pub struct FileType {
     pub language: String,
     pub is_binary: bool,
+    /// Markers that start a comment running to the end of the line.
     pub comment_patterns: Vec<&'static str>,
+    /// Whether line comment markers must start the line or follow whitespace.
+    pub spaced_line_comments: bool,
+    /// Open and close markers of block comments such as `/* ... */`.
+    pub block_comments: Vec<(&'static str, &'static str)>,
+    /// Markers that make a line or block comment documentation, such as `///` or `/**`.
+    pub doc_comments: Vec<&'static str>,
+    /// Whether block comments nest, as in Rust, Haskell and Swift.
+    pub nested_comments: bool,
+    /// Line prefixes that start test code inside a file, such as `#[cfg(test)]`.
+    pub test_markers: Vec<&'static str>,
+    /// Open and close delimiters of strings that honour backslash escapes.
+    /// Longer delimiters such as `"""` must come before their prefixes.
+    pub string_delimiters: Vec<(&'static str, &'static str)>,
+    /// Delimiters of strings without escapes, such as shell `'...'` or Go backticks.
+    pub verbatim_strings: Vec<(&'static str, &'static str)>,
+    /// Openers of strings that may continue past the end of their line.
+    pub multiline_strings: Vec<&'static str>,
+    /// Whether strings only open at the start of a value, as in YAML.
+    pub quoted_values: bool,
+    /// Whether Rust-style raw strings (`r"..."`, `r#"..."#`) are recognised.
+    pub raw_strings: bool,
+    /// Whether shell-style heredocs (`<<EOF ... EOF`) are recognised.
+    pub heredocs: bool,
+    /// How files of this type embed other languages, if they do.
+    pub embeds: Option<Embedding>,
 }
 
 impl FileType {
-    /// Detect file type and language from file path extension.
-    /// Maps common extensions to languages with their comment patterns.
+    /// File type described by a registry definition.
+    pub fn from_definition(definition: &'static LanguageDef) -> Self {
+        let pairs = |pairs: &'static [[String; 2]]| {
+            pairs
+                .iter()
+                .map(|[open, close]| (open.as_str(), close.as_str()))
+                .collect()
+        };
+
+        FileType {
+            language: definition.name.clone(),
+            is_binary: definition.binary,
+            comment_patterns: definition
+                .line_comments
+                .iter()
+                .map(String::as_str)
+                .collect(),
+            spaced_line_comments: definition.spaced_line_comments,
+            block_comments: pairs(&definition.block_comments),
+            doc_comments: definition.doc_comments.iter().map(String::as_str).collect(),
+            nested_comments: definition.nested_comments,
+            test_markers: definition.test_markers.iter().map(String::as_str).collect(),
+            string_delimiters: pairs(&definition.string_delimiters),
+            verbatim_strings: pairs(&definition.verbatim_strings),
+            multiline_strings: definition
+                .multiline_strings
+                .iter()
+                .map(String::as_str)
+                .collect(),
+            quoted_values: definition.quoted_values,
+            raw_strings: definition.raw_strings,
+            heredocs: definition.heredocs,
+            embeds: definition.embeds,
+        }
+    }
+
+    /// Detect file type and language from file path using the built-in definitions.
+    /// Well-known file names (Makefile, Dockerfile, ...) are checked first,
+    /// then the extension.
     pub fn from_path(path: &Path) -> Self {
-        let extension = path
-            .extension()
-            .and_then(|ext| ext.to_str())
-            .unwrap_or("")
-            .to_lowercase();
-
-        match extension.as_str() {
-            // Rust
-            "rs" => FileType {
-                language: "Rust".to_string(),
-                is_binary: false,
-                comment_patterns: vec!["//", "/*"],
-            },
-
-            // JavaScript/TypeScript
-            "js" | "jsx" | "mjs" => FileType {
-                language: "JavaScript".to_string(),
-                is_binary: false,
-                comment_patterns: vec!["//", "/*"],
-            },
-            "ts" | "tsx" => FileType {
-                language: "TypeScript".to_string(),
-                is_binary: false,
-                comment_patterns: vec!["//", "/*"],
-            },
-
-            // Python
-            "py" | "pyx" | "pyi" => FileType {
-                language: "Python".to_string(),
-                is_binary: false,
-                comment_patterns: vec!["#"],
-            },
-
-            // C/C++
-            "c" | "h" => FileType {
-                language: "C".to_string(),
-                is_binary: false,
-                comment_patterns: vec!["//", "/*"],
-            },
-            "cpp" | "cxx" | "cc" | "hpp" | "hxx" => FileType {
-                language: "C++".to_string(),
-                is_binary: false,
-                comment_patterns: vec!["//", "/*"],
-            },
-
-            // Java
-            "java" => FileType {
-                language: "Java".to_string(),
-                is_binary: false,
-                comment_patterns: vec!["//", "/*"],
-            },
-
-            // Go
-            "go" => FileType {
-                language: "Go".to_string(),
-                is_binary: false,
-                comment_patterns: vec!["//", "/*"],
-            },
-
-            // Shell
-            "sh" | "bash" | "zsh" | "fish" => FileType {
-                language: "Shell".to_string(),
-                is_binary: false,
-                comment_patterns: vec!["#"],
-            },
-
-            // Web languages
-            "html" | "htm" => FileType {
-                language: "HTML".to_string(),
-                is_binary: false,
-                comment_patterns: vec!["<!--"],
-            },
-            "css" => FileType {
-                language: "CSS".to_string(),
-                is_binary: false,
-                comment_patterns: vec!["/*"],
-            },
-            "scss" | "sass" => FileType {
-                language: "Sass".to_string(),
-                is_binary: false,
-                comment_patterns: vec!["//", "/*"],
-            },
-
-            // Config files
-            "json" => FileType {
-                language: "JSON".to_string(),
-                is_binary: false,
-                comment_patterns: vec![], // JSON doesn't support comments
-            },
-            "yaml" | "yml" => FileType {
-                language: "YAML".to_string(),
-                is_binary: false,
-                comment_patterns: vec!["#"],
-            },
-            "toml" => FileType {
-                language: "TOML".to_string(),
-                is_binary: false,
-                comment_patterns: vec!["#"],
-            },
-            "xml" => FileType {
-                language: "XML".to_string(),
-                is_binary: false,
-                comment_patterns: vec!["<!--"],
-            },
-
-            // Markup
-            "md" | "markdown" => FileType {
-                language: "Markdown".to_string(),
-                is_binary: false,
-                comment_patterns: vec!["<!--"],
-            },
-            "rst" => FileType {
-                language: "reStructuredText".to_string(),
-                is_binary: false,
-                comment_patterns: vec![".."],
-            },
-
-            // Other languages
-            "rb" => FileType {
-                language: "Ruby".to_string(),
-                is_binary: false,
-                comment_patterns: vec!["#"],
-            },
-            "php" => FileType {
-                language: "PHP".to_string(),
-                is_binary: false,
-                comment_patterns: vec!["//", "/*", "#"],
-            },
-            "swift" => FileType {
-                language: "Swift".to_string(),
-                is_binary: false,
-                comment_patterns: vec!["//", "/*"],
-            },
-            "kt" | "kts" => FileType {
-                language: "Kotlin".to_string(),
-                is_binary: false,
-                comment_patterns: vec!["//", "/*"],
-            },
-            "cs" => FileType {
-                language: "C#".to_string(),
-                is_binary: false,
-                comment_patterns: vec!["//", "/*"],
-            },
-            "dart" => FileType {
-                language: "Dart".to_string(),
-                is_binary: false,
-                comment_patterns: vec!["//", "/*"],
-            },
-            "r" => FileType {
-                language: "R".to_string(),
-                is_binary: false,
-                comment_patterns: vec!["#"],
-            },
-            "sql" => FileType {
-                language: "SQL".to_string(),
-                is_binary: false,
-                comment_patterns: vec!["--", "/*"],
-            },
-
-            // Binary files
-            "exe" | "dll" | "so" | "dylib" | "a" | "lib" => FileType {
-                language: "Binary".to_string(),
-                is_binary: true,
-                comment_patterns: vec![],
-            },
-            "jpg" | "jpeg" | "png" | "gif" | "bmp" | "svg" | "ico" | "webp" => FileType {
-                language: "Image".to_string(),
-                is_binary: true,
-                comment_patterns: vec![],
-            },
-            "mp3" | "wav" | "ogg" | "flac" | "aac" => FileType {
-                language: "Audio".to_string(),
-                is_binary: true,
-                comment_patterns: vec![],
-            },
-            "mp4" | "avi" | "mkv" | "mov" | "wmv" | "flv" => FileType {
-                language: "Video".to_string(),
-                is_binary: true,
-                comment_patterns: vec![],
-            },
-            "zip" | "tar" | "gz" | "bz2" | "xz" | "7z" | "rar" => FileType {
-                language: "Archive".to_string(),
-                is_binary: true,
-                comment_patterns: vec![],
-            },
-            "pdf" | "doc" | "docx" | "xls" | "xlsx" | "ppt" | "pptx" => FileType {
-                language: "Document".to_string(),
-                is_binary: true,
-                comment_patterns: vec![],
-            },
-
-            // Default for unknown files
-            _ => {
-                // Try to guess if it's binary by checking for common text file patterns
-                let is_likely_binary = self::is_likely_binary_extension(&extension);
-
-                FileType {
-                    language: if is_likely_binary { "Binary" } else { "Text" }.to_string(),
-                    is_binary: is_likely_binary,
-                    comment_patterns: vec!["#", "//"], // Default comment patterns
-                }
-            }
+        LanguageRegistry::builtin().detect_path(path)
+    }
+
+    /// Look up the file type for a well-known file name such as `Makefile`.
+    pub fn from_file_name(name: &str) -> Option<Self> {
+        LanguageRegistry::builtin().detect_file_name(name)
+    }
+
+    /// Look up the file type for a lowercase extension without the leading dot.
+    pub fn from_extension(extension: &str) -> Self {
+        LanguageRegistry::builtin().detect_extension(extension)
+    }
+
+    /// Detect the language of a script from its `#!` line.
+    pub fn from_shebang(first_line: &str) -> Option<Self> {
+        LanguageRegistry::builtin().detect_shebang(first_line)
+    }
+
+    /// Look up a language by name or common alias, case-insensitively.
+    /// Accepts the names used by vim `ft=`, Emacs `mode:` and linguist
+    /// (`ruby`, `cpp`, `C++`, `emacs-lisp`, ...) as well as known extensions.
+    pub fn from_language_name(name: &str) -> Option<Self> {
+        LanguageRegistry::builtin().lookup_name(name)
+    }
+
+    /// Detect a language declared by an editor modeline.
+    pub fn from_modeline(content: &str) -> Option<Self> {
+        LanguageRegistry::builtin().detect_modeline(content)
+    }
+
+    /// Binary file type in the given category (Binary, Image, Archive, ...).
+    pub fn binary(language: &str) -> Self {
+        FileType {
+            language: language.to_string(),
+            is_binary: true,
+            comment_patterns: vec![],
+            spaced_line_comments: false,
+            block_comments: vec![],
+            doc_comments: vec![],
+            nested_comments: false,
+            test_markers: vec![],
+            string_delimiters: vec![],
+            verbatim_strings: vec![],
+            multiline_strings: vec![],
+            quoted_values: false,
+            raw_strings: false,
+            heredocs: false,
+            embeds: None,
         }
     }
 
+    /// Generic text file type used when no language matches.
+    pub fn plain_text() -> Self {
+        LanguageRegistry::builtin().text()
+    }
+
     pub fn language(&self) -> &str {
         &self.language
     }
@@ -224,8 +140,21 @@ impl FileType {
         self.is_binary
     }
 
+    /// Whether this is the generic text type used when nothing else matched.
+    pub fn is_plain_text(&self) -> bool {
+        !self.is_binary && self.language == "Text"
+    }
+
+    /// Whether the file's content should be sniffed before trusting this type.
+    /// Media, archives and documents are trusted from their extension alone;
+    /// text types and generic "Binary" guesses are checked against the content.
+    pub fn needs_content_check(&self) -> bool {
+        !self.is_binary || self.language == "Binary"
+    }
+
     /// Check if a line is a comment based on language-specific patterns.
-    /// Supports single-line comments like //, #, --, etc.
+    /// Supports single-line comments like //, #, --, etc. and lines opening a block comment.
+    /// This looks at the line in isolation; use `LineLexer` to follow comments across lines.
     pub fn is_comment_line(&self, line: &str) -> bool {
         let trimmed = line.trim();
 
@@ -235,31 +164,12 @@ impl FileType {
             }
         }
 
-        false
+        self.block_comments
+            .iter()
+            .any(|(open, _)| trimmed.starts_with(open))
     }
 }
 
-fn is_likely_binary_extension(ext: &str) -> bool {
-    matches!(
-        ext,
-        "bin"
-            | "dat"
-            | "db"
-            | "sqlite"
-            | "sqlite3"
-            | "lock"
-            | "log"
-            | "tmp"
-            | "temp"
-            | "cache"
-            | "o"
-            | "obj"
-            | "pyc"
-            | "class"
-            | "jar"
-    )
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -300,10 +210,131 @@ mod tests {
     }
 
     #[test]
-    fn test_is_likely_binary_extension() {
-        assert!(is_likely_binary_extension("bin"));
-        assert!(is_likely_binary_extension("pyc"));
-        assert!(!is_likely_binary_extension("txt"));
-        assert!(!is_likely_binary_extension("rs"));
+    fn test_exact_file_name_rules() {
+        for language in LanguageRegistry::builtin().languages() {
+            for file_name in language.filenames.iter().filter(|name| !name.contains('*')) {
+                let by_name = FileType::from_path(Path::new("project").join(file_name).as_path());
+                assert_eq!(by_name.language, language.name, "{}", file_name);
+                assert_ne!(by_name.language, "Text", "{}", file_name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_file_name_glob_rules() {
+        let language = |name: &str| FileType::from_path(Path::new(name)).language;
+
+        assert_eq!(language("Dockerfile.dev"), "Dockerfile");
+        assert_eq!(language("api.Dockerfile"), "Dockerfile");
+        assert_eq!(language("Makefile.am"), "Makefile");
+        assert_eq!(language("Jenkinsfile.release"), "Groovy");
+        assert_eq!(language("tallyhawk.gemspec"), "Ruby");
+        assert_eq!(language("Dockerfile"), "Dockerfile");
+    }
+
+    #[test]
+    fn test_file_name_rules_beat_extensions() {
+        let cmake = FileType::from_path(Path::new("CMakeLists.txt"));
+        assert_eq!(cmake.language, "CMake");
+        assert!(cmake.comment_patterns.contains(&"#"));
+        assert!(cmake.block_comments.contains(&("#[[", "]]")));
+
+        let bazel = FileType::from_path(Path::new("BUILD.bazel"));
+        assert_eq!(bazel.language, "Starlark");
+        assert!(bazel.comment_patterns.contains(&"#"));
+
+        let jenkins = FileType::from_path(Path::new("Jenkinsfile"));
+        assert!(jenkins.comment_patterns.contains(&"//"));
+        assert!(jenkins.block_comments.contains(&("/*", "*/")));
+
+        let make = FileType::from_path(Path::new("Makefile"));
+        assert_eq!(make.comment_patterns, vec!["#"]);
+
+        assert_eq!(FileType::from_path(Path::new(".bashrc")).language, "Shell");
+        assert_eq!(FileType::from_path(Path::new("Gemfile")).language, "Ruby");
+        assert_eq!(FileType::from_path(Path::new("notes.txt")).language, "Text");
+    }
+
+    #[test]
+    fn test_shebang_detection() {
+        let language = |line: &str| FileType::from_shebang(line).map(|ft| ft.language);
+
+        assert_eq!(language("#!/bin/bash"), Some("Shell".to_string()));
+        assert_eq!(
+            language("#!/usr/bin/env python3"),
+            Some("Python".to_string())
+        );
+        assert_eq!(
+            language("#!/usr/bin/python3.11 -u"),
+            Some("Python".to_string())
+        );
+        assert_eq!(
+            language("#!/usr/bin/env node18"),
+            Some("JavaScript".to_string())
+        );
+        assert_eq!(
+            language("#!/usr/bin/env -S ruby --disable-gems"),
+            Some("Ruby".to_string())
+        );
+        assert_eq!(
+            language("#!/usr/bin/env -u HOME LANG=C bash"),
+            Some("Shell".to_string())
+        );
+        assert_eq!(language("#!/usr/bin/env -Sphp -n"), Some("PHP".to_string()));
+        assert_eq!(language("#!/usr/bin/env perl"), Some("Perl".to_string()));
+        assert_eq!(language("# plain comment"), None);
+    }
+
+    #[test]
+    fn test_language_name_lookup() {
+        let language = |name: &str| FileType::from_language_name(name).map(|ft| ft.language);
+
+        assert_eq!(language("PHP"), Some("PHP".to_string()));
+        assert_eq!(language("cpp"), Some("C++".to_string()));
+        assert_eq!(language("C++"), Some("C++".to_string()));
+        assert_eq!(language("emacs-lisp"), Some("Lisp".to_string()));
+        assert_eq!(language("python-mode"), Some("Python".to_string()));
+        assert_eq!(language("rb"), Some("Ruby".to_string()));
+        assert_eq!(language("klingon"), None);
+    }
+
+    #[test]
+    fn test_modeline_detection() {
+        let language = |content: &str| FileType::from_modeline(content).map(|ft| ft.language);
+
+        assert_eq!(
+            language("# vim: set ft=ruby:\nputs 1"),
+            Some("Ruby".to_string())
+        );
+        assert_eq!(
+            language("echo hi\n# vi: filetype=sh ts=4"),
+            Some("Shell".to_string())
+        );
+        assert_eq!(
+            language(";; -*- mode: lisp; coding: utf-8 -*-\n(defun f ())"),
+            Some("Lisp".to_string())
+        );
+        assert_eq!(
+            language("# -*- python -*-\nx = 1"),
+            Some("Python".to_string())
+        );
+        assert_eq!(language("let environment = 1; // servim: ft=c"), None);
+        assert_eq!(language("no modeline here"), None);
+    }
+
+    #[test]
+    fn test_needs_content_check() {
+        assert!(FileType::from_path(Path::new("main.rs")).needs_content_check());
+        assert!(FileType::from_path(Path::new("data.bin")).needs_content_check());
+        assert!(FileType::from_path(Path::new("blob")).needs_content_check());
+        assert!(!FileType::from_path(Path::new("logo.png")).needs_content_check());
+    }
+
+    #[test]
+    fn test_likely_binary_extensions() {
+        assert!(FileType::from_extension("bin").is_binary());
+        assert!(FileType::from_extension("pyc").is_binary());
+        assert!(!FileType::from_extension("txt").is_binary());
+        assert!(!FileType::from_extension("rs").is_binary());
     }
 }
@@ -0,0 +1,222 @@
+use crate::stats::registry::LanguageRegistry;
+use globset::{GlobBuilder, GlobMatcher};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// The filters a scan ran with, as recorded in the JSON output.
+/// Language names are the canonical registry names, not what was typed.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanFilters {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub languages: Vec<String>,
+    pub excluded_languages: Vec<String>,
+}
+
+impl ScanFilters {
+    /// Validate the globs and resolve the language names of a scan.
+    /// Besides languages, `Text` and the binary categories (`Image`, ...) are accepted.
+    pub fn new(
+        include: &[String],
+        exclude: &[String],
+        languages: &[String],
+        excluded_languages: &[String],
+        registry: &'static LanguageRegistry,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let resolve = |names: &[String], flag: &str| -> Result<Vec<String>, String> {
+            names
+                .iter()
+                .map(|name| {
+                    registry
+                        .lookup_name(name)
+                        .map(|file_type| file_type.language)
+                        .or_else(|| registry.definition_name(name).map(str::to_string))
+                        .ok_or_else(|| format!("unknown language '{}' in {}", name, flag))
+                })
+                .collect()
+        };
+
+        let filters = Self {
+            include: include.to_vec(),
+            exclude: exclude.to_vec(),
+            languages: resolve(languages, "--lang")?,
+            excluded_languages: resolve(excluded_languages, "--exclude-lang")?,
+        };
+        filters.path_filter()?;
+        Ok(filters)
+    }
+
+    /// Whether files of a language are part of the scan.
+    pub fn allows_language(&self, language: &str) -> bool {
+        (self.languages.is_empty() || self.languages.iter().any(|name| name == language))
+            && !self.excluded_languages.iter().any(|name| name == language)
+    }
+
+    /// Compile the include and exclude globs.
+    pub fn path_filter(&self) -> Result<PathFilter, globset::Error> {
+        Ok(PathFilter {
+            include: PathPatterns::new(&self.include)?,
+            exclude: PathPatterns::new(&self.exclude)?,
+        })
+    }
+}
+
+/// Include and exclude globs, matched against paths relative to the scan root.
+#[derive(Debug, Clone)]
+pub struct PathFilter {
+    include: PathPatterns,
+    exclude: PathPatterns,
+}
+
+impl PathFilter {
+    /// Whether to walk a path. Excluded directories are pruned whole; the
+    /// include globs only select files, which match when they or one of
+    /// their directories do.
+    pub fn allows(&self, relative_path: &Path, is_dir: bool) -> bool {
+        if relative_path.as_os_str().is_empty() {
+            return true;
+        }
+        if self.exclude.is_match(relative_path) {
+            return false;
+        }
+        is_dir || self.include.is_empty() || self.include.is_match_or_parent(relative_path)
+    }
+}
+
+/// Globs matched gitignore-style against paths relative to the scan root:
+/// a pattern without a `/` matches the file name at any depth, and a glob
+/// ending in `/**` also matches the directory itself.
+#[derive(Debug, Clone)]
+pub struct PathPatterns {
+    patterns: Vec<Pattern>,
+}
+
+#[derive(Debug, Clone)]
+struct Pattern {
+    matcher: GlobMatcher,
+    basename_only: bool,
+}
+
+impl Pattern {
+    fn is_match(&self, path: &Path) -> bool {
+        if self.basename_only {
+            path.file_name()
+                .is_some_and(|name| self.matcher.is_match(name))
+        } else {
+            self.matcher.is_match(path)
+        }
+    }
+}
+
+impl PathPatterns {
+    pub fn new(patterns: &[String]) -> Result<Self, globset::Error> {
+        let mut compiled = Vec::new();
+        for pattern in patterns {
+            let basename_only = !pattern.trim_end_matches('/').contains('/');
+            let glob = pattern.trim_start_matches('/').trim_end_matches('/');
+
+            let globs = match glob.strip_suffix("/**") {
+                Some(directory) if !directory.is_empty() => vec![glob, directory],
+                _ => vec![glob],
+            };
+            for glob in globs {
+                let matcher = GlobBuilder::new(glob)
+                    .literal_separator(true)
+                    .build()?
+                    .compile_matcher();
+                compiled.push(Pattern {
+                    matcher,
+                    basename_only,
+                });
+            }
+        }
+        Ok(Self { patterns: compiled })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Whether any pattern matches the path itself.
+    pub fn is_match(&self, relative_path: &Path) -> bool {
+        self.patterns
+            .iter()
+            .any(|pattern| pattern.is_match(relative_path))
+    }
+
+    /// Whether any pattern matches the path or one of its directories.
+    pub fn is_match_or_parent(&self, relative_path: &Path) -> bool {
+        relative_path
+            .ancestors()
+            .filter(|path| !path.as_os_str().is_empty())
+            .any(|path| self.is_match(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filters(include: &[&str], exclude: &[&str]) -> PathFilter {
+        let strings = |patterns: &[&str]| patterns.iter().map(|p| p.to_string()).collect();
+        let filters = ScanFilters {
+            include: strings(include),
+            exclude: strings(exclude),
+            ..Default::default()
+        };
+        filters.path_filter().unwrap()
+    }
+
+    #[test]
+    fn test_path_filter() {
+        let filter = filters(&["src/**", "build.rs"], &["**/generated/**", "*.min.js"]);
+        assert!(filter.allows(Path::new("src/main.rs"), false));
+        assert!(filter.allows(Path::new("build.rs"), false));
+        assert!(filter.allows(Path::new("tools/build.rs"), false));
+        assert!(!filter.allows(Path::new("tests/it.rs"), false));
+        assert!(filter.allows(Path::new("tests"), true));
+        assert!(!filter.allows(Path::new("src/generated"), true));
+        assert!(!filter.allows(Path::new("src/app.min.js"), false));
+
+        let directory = filters(&["crates/core"], &[]);
+        assert!(directory.allows(Path::new("crates/core/src/lib.rs"), false));
+        assert!(!directory.allows(Path::new("crates/cli/src/main.rs"), false));
+    }
+
+    #[test]
+    fn test_language_filters() {
+        let registry = LanguageRegistry::builtin();
+        let none: &[String] = &[];
+        let filters = ScanFilters::new(
+            none,
+            none,
+            &["rust".to_string(), "py".to_string()],
+            none,
+            registry,
+        )
+        .unwrap();
+        assert_eq!(filters.languages, vec!["Rust", "Python"]);
+        assert!(filters.allows_language("Rust"));
+        assert!(!filters.allows_language("JSON"));
+
+        let filters = ScanFilters::new(none, none, none, &["JSON".to_string()], registry).unwrap();
+        assert!(filters.allows_language("Rust"));
+        assert!(!filters.allows_language("JSON"));
+
+        let filters = ScanFilters::new(
+            none,
+            none,
+            none,
+            &["text".to_string(), "Image".to_string()],
+            registry,
+        )
+        .unwrap();
+        assert_eq!(filters.excluded_languages, vec!["Text", "Image"]);
+        assert!(!filters.allows_language("Text"));
+
+        let error =
+            ScanFilters::new(none, none, &["Klingon".to_string()], none, registry).unwrap_err();
+        assert!(error.to_string().contains("--lang"));
+        assert!(ScanFilters::new(&["src/[".to_string()], none, none, none, registry).is_err());
+    }
+}
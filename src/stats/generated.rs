@@ -0,0 +1,238 @@
+use std::fmt;
+use std::path::Path;
+
+/// File names written by package managers and other tools.
+const GENERATED_NAMES: &[&str] = &[
+    "Cargo.lock",
+    "composer.lock",
+    "flake.lock",
+    "Gemfile.lock",
+    "go.sum",
+    "package-lock.json",
+    "packages.lock.json",
+    "Pipfile.lock",
+    "pnpm-lock.yaml",
+    "poetry.lock",
+    "yarn.lock",
+];
+
+/// File name endings of minified assets and compiler output such as protobuf stubs.
+const GENERATED_SUFFIXES: &[&str] = &[
+    ".min.js",
+    ".min.mjs",
+    ".min.css",
+    ".pb.go",
+    ".pb.cc",
+    ".pb.h",
+    ".pb.swift",
+    "_pb2.py",
+    "_pb2_grpc.py",
+    "_pb.js",
+    "_pb.d.ts",
+    ".g.dart",
+    ".designer.cs",
+];
+
+/// Go's standard header: `// Code generated <tool>. DO NOT EDIT.` on a line of its own.
+const GO_HEADER: (&str, &str) = ("// Code generated ", " DO NOT EDIT.");
+
+/// Notice that must follow a comment leader to count, compared in lowercase.
+const GENERATED_NOTICE: &str = "this file was automatically generated";
+
+/// Comment leaders a generated-file notice may follow.
+const COMMENT_LEADERS: &[&str] = &["//", "#", "/*", "*", "--", ";", "<!--", "%"];
+
+/// How many lines from the top of a file are searched for header markers.
+const HEADER_LINES: usize = 10;
+
+/// Files shorter than this are never considered minified.
+const MINIFIED_MIN_LEN: usize = 512;
+/// Average line length above which a file may be minified.
+const MINIFIED_LINE_LEN: usize = 200;
+/// Minified code leaves out nearly all whitespace...
+const MINIFIED_MAX_WHITESPACE: f64 = 0.1;
+/// ...but, unlike padding or runs of one character, is not repetitive.
+const MINIFIED_MIN_ENTROPY: f64 = 4.0;
+
+/// Why a file was considered generated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Generated {
+    /// Marked `linguist-generated` in `.gitattributes`.
+    Attribute,
+    /// A lockfile or generated-code file name.
+    FileName(&'static str),
+    /// A marker such as `@generated` near the top of the file.
+    Header(&'static str),
+    /// Long lines with hardly any whitespace.
+    Minified,
+}
+
+impl fmt::Display for Generated {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Generated::Attribute => write!(f, "generated (linguist-generated)"),
+            Generated::FileName(name) => write!(f, "generated (file name `{}`)", name),
+            Generated::Header(marker) => write!(f, "generated (found `{}`)", marker),
+            Generated::Minified => write!(f, "generated (minified)"),
+        }
+    }
+}
+
+/// Detect generated files from their name alone.
+pub fn detect_path(path: &Path) -> Option<Generated> {
+    let file_name = path.file_name()?.to_str()?;
+    GENERATED_NAMES
+        .iter()
+        .find(|&&name| name == file_name)
+        .or_else(|| {
+            let lower = file_name.to_lowercase();
+            GENERATED_SUFFIXES
+                .iter()
+                .find(|&&suffix| lower.ends_with(suffix))
+        })
+        .map(|&name| Generated::FileName(name))
+}
+
+/// Detect generated files from header markers and minified content.
+/// Only the canonical headers count, so prose that merely mentions
+/// generated code (`IDs are auto-generated by the database`) does not.
+pub fn detect_content(content: &str) -> Option<Generated> {
+    if let Some(marker) = content.lines().take(HEADER_LINES).find_map(header_marker) {
+        return Some(Generated::Header(marker));
+    }
+
+    is_minified(content).then_some(Generated::Minified)
+}
+
+/// The generated-file header on one line, if it holds one.
+fn header_marker(line: &str) -> Option<&'static str> {
+    let line = line.trim();
+    let (go_prefix, go_suffix) = GO_HEADER;
+    if line.starts_with(go_prefix) && line.ends_with(go_suffix) {
+        return Some("Code generated ... DO NOT EDIT.");
+    }
+    if line.contains("@generated") {
+        return Some("@generated");
+    }
+
+    let body = COMMENT_LEADERS
+        .iter()
+        .find_map(|leader| line.strip_prefix(leader))?
+        .trim_start_matches(|c: char| "/#*-;!%".contains(c) || c.is_whitespace());
+    body.to_lowercase()
+        .starts_with(GENERATED_NOTICE)
+        .then_some(GENERATED_NOTICE)
+}
+
+/// Long lines, little whitespace and a varied alphabet: the shape of
+/// minified JavaScript and CSS, but not of prose or long data tables.
+fn is_minified(content: &str) -> bool {
+    let line_count = content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .count();
+    if content.len() < MINIFIED_MIN_LEN || line_count == 0 {
+        return false;
+    }
+    if content.len() / line_count <= MINIFIED_LINE_LEN {
+        return false;
+    }
+
+    let whitespace = content.bytes().filter(u8::is_ascii_whitespace).count();
+    if whitespace as f64 / content.len() as f64 >= MINIFIED_MAX_WHITESPACE {
+        return false;
+    }
+
+    entropy(content.as_bytes()) >= MINIFIED_MIN_ENTROPY
+}
+
+/// Shannon entropy of the bytes, in bits per byte.
+fn entropy(bytes: &[u8]) -> f64 {
+    let mut counts = [0usize; 256];
+    for &byte in bytes {
+        counts[byte as usize] += 1;
+    }
+
+    let len = bytes.len() as f64;
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_file_names() {
+        assert_eq!(
+            detect_path(Path::new("web/yarn.lock")),
+            Some(Generated::FileName("yarn.lock"))
+        );
+        assert_eq!(
+            detect_path(Path::new("api/user.pb.go")),
+            Some(Generated::FileName(".pb.go"))
+        );
+        assert_eq!(
+            detect_path(Path::new("static/App.MIN.js")),
+            Some(Generated::FileName(".min.js"))
+        );
+        assert_eq!(detect_path(Path::new("src/main.rs")), None);
+    }
+
+    #[test]
+    fn test_generated_headers() {
+        assert_eq!(
+            detect_content("// Code generated by protoc-gen-go. DO NOT EDIT.\npackage api\n"),
+            Some(Generated::Header("Code generated ... DO NOT EDIT."))
+        );
+        assert_eq!(
+            detect_content("# @generated by a tool\nx = 1\n"),
+            Some(Generated::Header("@generated"))
+        );
+        assert_eq!(
+            detect_content("/*\n * This file was automatically generated by SWIG.\n */\n"),
+            Some(Generated::Header(GENERATED_NOTICE))
+        );
+
+        // Prose about generated things is not a header
+        for content in [
+            "// IDs are auto-generated by the database\nstruct Id;\n",
+            "# do not edit this list without updating X\nitems = []\n",
+            "// Code generated by hand; please DO NOT EDIT. carelessly\n",
+            "This file was automatically generated, then edited by hand.\n",
+            "Autogenerated values are rejected.\n",
+        ] {
+            assert_eq!(detect_content(content), None, "{}", content);
+        }
+
+        let late = format!("{}// DO NOT EDIT\n", "x = 1\n".repeat(HEADER_LINES));
+        assert_eq!(detect_content(&late), None);
+    }
+
+    #[test]
+    fn test_minified_content() {
+        let minified: String = (0..60)
+            .map(|i| {
+                format!(
+                    "function f{i}(a,b){{return a*{i}+b.x{i}||[]}};var v{i}=f{i}(1,{{x{i}:2}});"
+                )
+            })
+            .collect();
+        assert_eq!(detect_content(&minified), Some(Generated::Minified));
+
+        let prose = "This paragraph was written on one long line, as Markdown allows, so the line is long but full of spaces. ".repeat(10);
+        assert_eq!(detect_content(&prose), None);
+
+        let padding = "=".repeat(2000);
+        assert_eq!(detect_content(&padding), None);
+
+        let source = "fn main() {\n    println!(\"hi\");\n}\n".repeat(40);
+        assert_eq!(detect_content(&source), None);
+    }
+}
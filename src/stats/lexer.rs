@@ -0,0 +1,697 @@
+use crate::stats::file_types::FileType;
+
+/// Classification of a single source line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineKind {
+    Blank,
+    Code,
+    Comment,
+    /// Documentation comment such as `///`, `/** */` or a Python docstring.
+    DocComment,
+    /// Code followed or preceded by a comment on the same line.
+    Mixed,
+}
+
+/// Where the lexer is when a line ends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum State {
+    Code,
+    BlockComment {
+        open: &'static str,
+        close: &'static str,
+        depth: usize,
+        doc: bool,
+    },
+    String {
+        close: &'static str,
+        escapes: bool,
+        multiline: bool,
+    },
+    RawString {
+        hashes: usize,
+    },
+    Heredoc {
+        terminator: String,
+    },
+}
+
+/// Stateful line classifier for one file.
+/// Tracks open block comments across lines, including nested ones for
+/// languages that allow them, and skips over string literals so that comment
+/// markers inside strings do not count as comments.
+pub struct LineLexer<'a> {
+    file_type: &'a FileType,
+    state: State,
+    /// Bytes that may start a comment, string or literal. Runs of code
+    /// without them are skipped in one go, and lines without them at all
+    /// are code without walking them.
+    start_bytes: [bool; 256],
+}
+
+impl<'a> LineLexer<'a> {
+    pub fn new(file_type: &'a FileType) -> Self {
+        let mut start_bytes = [false; 256];
+        let openers = file_type
+            .comment_patterns
+            .iter()
+            .chain(file_type.block_comments.iter().map(|(open, _)| open))
+            .chain(file_type.string_delimiters.iter().map(|(open, _)| open))
+            .chain(file_type.verbatim_strings.iter().map(|(open, _)| open));
+        for opener in openers {
+            start_bytes[opener.as_bytes()[0] as usize] = true;
+        }
+        // Char literals, raw strings (`r"`, `br"`) and heredocs (`<<EOF`)
+        start_bytes[b'\'' as usize] = true;
+        if file_type.raw_strings {
+            for byte in [b'r', b'b', b'"'] {
+                start_bytes[byte as usize] = true;
+            }
+        }
+        if file_type.heredocs {
+            start_bytes[b'<' as usize] = true;
+        }
+
+        Self {
+            file_type,
+            state: State::Code,
+            start_bytes,
+        }
+    }
+
+    /// Classify the next line of the file and advance the lexer state.
+    pub fn classify(&mut self, line: &str) -> LineKind {
+        if line.trim().is_empty() {
+            return LineKind::Blank;
+        }
+
+        if self.state == State::Code && !line.bytes().any(|b| self.start_bytes[b as usize]) {
+            return LineKind::Code;
+        }
+
+        // Heredoc bodies are string content; only the terminator line ends them.
+        if let State::Heredoc { terminator } = &self.state {
+            if line.trim() == terminator {
+                self.state = State::Code;
+            }
+            return LineKind::Code;
+        }
+
+        let mut has_code = false;
+        let mut has_comment = false;
+        let mut has_doc_comment = false;
+        let mut pending_heredoc = None;
+        let mut i = 0;
+
+        while i < line.len() {
+            let rest = &line[i..];
+
+            match self.state {
+                State::BlockComment {
+                    open,
+                    close,
+                    depth,
+                    doc,
+                } => {
+                    if doc {
+                        has_doc_comment = true;
+                    } else {
+                        has_comment = true;
+                    }
+
+                    if self.file_type.nested_comments && rest.starts_with(open) {
+                        self.state = State::BlockComment {
+                            open,
+                            close,
+                            depth: depth + 1,
+                            doc,
+                        };
+                        i += open.len();
+                    } else if rest.starts_with(close) {
+                        self.state = if depth > 1 {
+                            State::BlockComment {
+                                open,
+                                close,
+                                depth: depth - 1,
+                                doc,
+                            }
+                        } else {
+                            State::Code
+                        };
+                        i += close.len();
+                    } else {
+                        i += char_len(rest);
+                    }
+                }
+                State::String { close, escapes, .. } => {
+                    has_code = true;
+
+                    if escapes && rest.starts_with('\\') {
+                        i += 1 + rest[1..].chars().next().map_or(0, char::len_utf8);
+                    } else if rest.starts_with(close) {
+                        self.state = State::Code;
+                        i += close.len();
+                    } else {
+                        i += char_len(rest);
+                    }
+                }
+                State::RawString { hashes } => {
+                    has_code = true;
+
+                    if rest.starts_with('"')
+                        && rest[1..].bytes().take_while(|&b| b == b'#').count() >= hashes
+                    {
+                        self.state = State::Code;
+                        i += 1 + hashes;
+                    } else {
+                        i += char_len(rest);
+                    }
+                }
+                State::Heredoc { .. } => unreachable!("heredoc bodies start on the next line"),
+                State::Code => {
+                    if let Some((open, close)) = self.block_comment_at(rest, has_code) {
+                        // `/**/` is an empty comment, not documentation
+                        let doc =
+                            self.is_doc_comment(rest) && !rest[open.len()..].starts_with(close);
+                        if doc {
+                            has_doc_comment = true;
+                        } else {
+                            has_comment = true;
+                        }
+                        self.state = State::BlockComment {
+                            open,
+                            close,
+                            depth: 1,
+                            doc,
+                        };
+                        i += open.len();
+                    } else if self.line_comment_at(line, i) {
+                        if self.is_doc_comment(rest) {
+                            has_doc_comment = true;
+                        } else {
+                            has_comment = true;
+                        }
+                        break;
+                    } else if let Some((len, hashes)) = self.raw_string_at(line, i) {
+                        has_code = true;
+                        self.state = State::RawString { hashes };
+                        i += len;
+                    } else if let Some((len, terminator)) = self.heredoc_at(line, i) {
+                        has_code = true;
+                        pending_heredoc = Some(terminator);
+                        i += len;
+                    } else if let Some((open, close, escapes)) = self.string_at(line, i) {
+                        has_code = true;
+                        self.state = State::String {
+                            close,
+                            escapes,
+                            multiline: self.file_type.multiline_strings.contains(&open),
+                        };
+                        i += open.len();
+                    } else if let Some(len) = char_literal_len(rest) {
+                        has_code = true;
+                        i += len;
+                    } else {
+                        // Nothing can start before the next start byte; those
+                        // are ASCII or lead bytes, so the run ends on a char boundary
+                        let run = rest
+                            .bytes()
+                            .skip(1)
+                            .position(|b| self.start_bytes[b as usize])
+                            .map_or(rest.len(), |position| position + 1);
+                        if !rest[..run].trim().is_empty() {
+                            has_code = true;
+                        }
+                        i += run;
+                    }
+                }
+            }
+        }
+
+        // Only some strings may span lines; an unterminated one ends with its
+        // line, so a stray apostrophe cannot swallow the rest of the file.
+        if let State::String {
+            multiline: false, ..
+        } = self.state
+        {
+            if !line.trim_end().ends_with('\\') {
+                self.state = State::Code;
+            }
+        }
+
+        if let Some(terminator) = pending_heredoc {
+            if self.state == State::Code {
+                self.state = State::Heredoc { terminator };
+            }
+        }
+
+        if has_code && (has_comment || has_doc_comment) {
+            LineKind::Mixed
+        } else if has_code {
+            LineKind::Code
+        } else if has_doc_comment {
+            LineKind::DocComment
+        } else if has_comment {
+            LineKind::Comment
+        } else {
+            LineKind::Blank
+        }
+    }
+
+    /// Block comment opening at the start of `rest`.
+    /// An opener that doubles as a string delimiter, like Python's `"""`, only
+    /// starts a comment when no code precedes it on the line; otherwise it is a string.
+    fn block_comment_at(&self, rest: &str, has_code: bool) -> Option<(&'static str, &'static str)> {
+        self.file_type
+            .block_comments
+            .iter()
+            .find(|(open, _)| {
+                rest.starts_with(open)
+                    && !(has_code
+                        && self
+                            .file_type
+                            .string_delimiters
+                            .iter()
+                            .any(|(d, _)| d == open))
+            })
+            .copied()
+    }
+
+    /// Whether a line comment starts at byte `i` of `line`. Languages with
+    /// `spaced_line_comments` only accept a marker at the start of the line or
+    /// after whitespace, so `echo $#` and `http://` are not comments.
+    fn line_comment_at(&self, line: &str, i: usize) -> bool {
+        if self.file_type.spaced_line_comments
+            && !line[..i]
+                .chars()
+                .next_back()
+                .is_none_or(char::is_whitespace)
+        {
+            return false;
+        }
+
+        self.file_type
+            .comment_patterns
+            .iter()
+            .any(|pattern| line[i..].starts_with(pattern))
+    }
+
+    /// Whether the comment starting at `rest` opens with a doc-comment marker.
+    /// A marker followed by its own last character (`////`, `/***`) is
+    /// decoration, not documentation.
+    fn is_doc_comment(&self, rest: &str) -> bool {
+        self.file_type.doc_comments.iter().any(|marker| {
+            rest.strip_prefix(marker).is_some_and(|after| {
+                !marker
+                    .chars()
+                    .next_back()
+                    .is_some_and(|last| after.starts_with(last))
+            })
+        })
+    }
+
+    /// String literal opening at byte `i` of `line`, with whether it honours escapes.
+    /// In languages with `quoted_values` a quote only opens a string where a
+    /// value starts: at the start of the line or after `:`, `-`, `[`, `{`, `,` or `?`.
+    fn string_at(&self, line: &str, i: usize) -> Option<(&'static str, &'static str, bool)> {
+        if self.file_type.quoted_values {
+            let before = line[..i].trim_end();
+            if !(before.is_empty() || before.ends_with([':', '-', '[', '{', ',', '?'])) {
+                return None;
+            }
+        }
+
+        let rest = &line[i..];
+        let escaped = self
+            .file_type
+            .string_delimiters
+            .iter()
+            .map(|&(open, close)| (open, close, true));
+        let verbatim = self
+            .file_type
+            .verbatim_strings
+            .iter()
+            .map(|&(open, close)| (open, close, false));
+
+        escaped
+            .chain(verbatim)
+            .find(|(open, _, _)| rest.starts_with(open))
+    }
+
+    /// Rust raw string (`r"`, `r#"`, `br##"`) starting at byte `i` of `line`.
+    /// Returns the opener length and the number of `#`s the closer needs.
+    fn raw_string_at(&self, line: &str, i: usize) -> Option<(usize, usize)> {
+        if !self.file_type.raw_strings {
+            return None;
+        }
+
+        let preceded_by_ident = line[..i]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_');
+        if preceded_by_ident {
+            return None;
+        }
+
+        let rest = &line[i..];
+        let prefix = if rest.starts_with("br") {
+            2
+        } else if rest.starts_with('r') {
+            1
+        } else {
+            return None;
+        };
+
+        let hashes = rest[prefix..].bytes().take_while(|&b| b == b'#').count();
+        if rest[prefix + hashes..].starts_with('"') {
+            Some((prefix + hashes + 1, hashes))
+        } else {
+            None
+        }
+    }
+
+    /// Shell heredoc introducer (`<<EOF`, `<<-EOF`, `<< 'EOF'`) at byte `i` of `line`.
+    /// Returns the introducer length and the terminator word.
+    /// A `<<` right after an operand (`x<<n`) or inside `((`/`$((` arithmetic
+    /// is a shift, not a heredoc.
+    fn heredoc_at(&self, line: &str, i: usize) -> Option<(usize, String)> {
+        let rest = &line[i..];
+        if !self.file_type.heredocs || !rest.starts_with("<<") || rest.starts_with("<<<") {
+            return None;
+        }
+
+        let before = &line[..i];
+        let after_operand = before
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_alphanumeric() || matches!(c, '_' | ')' | ']' | '}'));
+        let in_arithmetic = before.matches("((").count() > before.matches("))").count();
+        if after_operand || in_arithmetic {
+            return None;
+        }
+
+        let after = rest[2..].strip_prefix('-').unwrap_or(&rest[2..]);
+        let word_start = after.trim_start();
+        let (quote, word_start) = match word_start.chars().next() {
+            Some(q @ ('\'' | '"')) => (Some(q), &word_start[1..]),
+            _ => (None, word_start),
+        };
+
+        let word_len = word_start
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(word_start.len());
+        let word = &word_start[..word_len];
+        if !word.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+            return None;
+        }
+
+        let mut len = rest.len() - word_start.len() + word_len;
+        if let Some(q) = quote {
+            if !word_start[word_len..].starts_with(q) {
+                return None;
+            }
+            len += 1;
+        }
+
+        Some((len, word.to_string()))
+    }
+}
+
+/// Length of a character literal such as `'"'` or `'\''` at the start of `rest`.
+/// Lets languages whose `'` is not a string delimiter (Rust, Go, Haskell) skip
+/// quotes inside char literals without mistaking lifetimes for strings.
+fn char_literal_len(rest: &str) -> Option<usize> {
+    let body = rest.strip_prefix('\'')?;
+
+    let len = if let Some(escaped) = body.strip_prefix('\\') {
+        let skip = char_len(escaped);
+        let close = escaped.get(skip..)?.find('\'')?;
+        // Escapes are short (`\n`, `\u{1F600}`); anything longer is not a literal.
+        if close > 8 {
+            return None;
+        }
+        1 + skip + close
+    } else {
+        let c = body.chars().next()?;
+        if c == '\'' || !body[c.len_utf8()..].starts_with('\'') {
+            return None;
+        }
+        c.len_utf8()
+    };
+
+    Some(1 + len + 1)
+}
+
+fn char_len(s: &str) -> usize {
+    s.chars().next().map_or(1, char::len_utf8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn classify_all(file_name: &str, source: &str) -> Vec<LineKind> {
+        let file_type = FileType::from_path(Path::new(file_name));
+        let mut lexer = LineLexer::new(&file_type);
+        source.lines().map(|line| lexer.classify(line)).collect()
+    }
+
+    #[test]
+    fn test_multi_line_block_comment() {
+        let kinds = classify_all("main.c", "/*\n * header\n\n */\nint x;");
+        assert_eq!(
+            kinds,
+            vec![
+                LineKind::Comment,
+                LineKind::Comment,
+                LineKind::Blank,
+                LineKind::Comment,
+                LineKind::Code
+            ]
+        );
+    }
+
+    #[test]
+    fn test_code_after_block_comment_closes() {
+        let kinds = classify_all("main.c", "/* start\n end */ int x;\nint y;");
+        assert_eq!(
+            kinds,
+            vec![LineKind::Comment, LineKind::Mixed, LineKind::Code]
+        );
+    }
+
+    #[test]
+    fn test_nested_block_comments() {
+        let source = "/* outer\n/* inner */\nstill outer */\nfn main() {}";
+        assert_eq!(
+            classify_all("lib.rs", source),
+            vec![
+                LineKind::Comment,
+                LineKind::Comment,
+                LineKind::Comment,
+                LineKind::Code
+            ]
+        );
+
+        // C does not nest, so the first `*/` ends the comment.
+        assert_eq!(
+            classify_all("main.c", source),
+            vec![
+                LineKind::Comment,
+                LineKind::Comment,
+                LineKind::Code,
+                LineKind::Code
+            ]
+        );
+    }
+
+    #[test]
+    fn test_haskell_nested_comments() {
+        let source = "{- a\n{- b -}\n-}\n-- line\nmain = pure ()";
+        assert_eq!(
+            classify_all("Main.hs", source),
+            vec![
+                LineKind::Comment,
+                LineKind::Comment,
+                LineKind::Comment,
+                LineKind::Comment,
+                LineKind::Code
+            ]
+        );
+    }
+
+    #[test]
+    fn test_html_and_python_blocks() {
+        assert_eq!(
+            classify_all("index.html", "<!--\nnote\n--><p>hi</p>"),
+            vec![LineKind::Comment, LineKind::Comment, LineKind::Mixed]
+        );
+        assert_eq!(
+            classify_all("mod.py", "\"\"\"\nDocs\n\"\"\"\nx = 1"),
+            vec![
+                LineKind::DocComment,
+                LineKind::DocComment,
+                LineKind::DocComment,
+                LineKind::Code
+            ]
+        );
+    }
+
+    #[test]
+    fn test_comment_markers_inside_strings() {
+        assert_eq!(
+            classify_all("app.py", "s = \"\"\"\n# not a comment\n\"\"\"\n# real"),
+            vec![
+                LineKind::Code,
+                LineKind::Code,
+                LineKind::Code,
+                LineKind::Comment
+            ]
+        );
+        assert_eq!(
+            classify_all(
+                "app.js",
+                "const url = `\n// not a comment\n`;\nlet s = \"/* \\\" */\";"
+            ),
+            vec![
+                LineKind::Code,
+                LineKind::Code,
+                LineKind::Code,
+                LineKind::Code
+            ]
+        );
+        assert_eq!(
+            classify_all(
+                "config.yaml",
+                "name: it's here\n# comment one\nkey: 'a # b'\n# comment two"
+            ),
+            vec![
+                LineKind::Code,
+                LineKind::Comment,
+                LineKind::Code,
+                LineKind::Comment
+            ]
+        );
+        assert_eq!(
+            classify_all(
+                "main.c",
+                "char *s = \"open\n// comment\nputs(\"a\\\n// b\");"
+            ),
+            vec![
+                LineKind::Code,
+                LineKind::Comment,
+                LineKind::Code,
+                LineKind::Code
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rust_raw_strings_and_char_literals() {
+        let source = "let s = r#\"\n// url \" still raw\n\"#;\nlet c = '\"'; // quote\nlet q = '\\''; let s = \"/*\";\nfn f<'a>(x: &'a str) {}";
+        assert_eq!(
+            classify_all("lib.rs", source),
+            vec![
+                LineKind::Code,
+                LineKind::Code,
+                LineKind::Code,
+                LineKind::Mixed,
+                LineKind::Code,
+                LineKind::Code
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shell_heredoc() {
+        let source = "cat <<'EOF'\n# inside heredoc\nEOF\n# comment";
+        assert_eq!(
+            classify_all("run.sh", source),
+            vec![
+                LineKind::Code,
+                LineKind::Code,
+                LineKind::Code,
+                LineKind::Comment
+            ]
+        );
+    }
+
+    #[test]
+    fn test_line_comments_need_leading_whitespace() {
+        assert_eq!(
+            classify_all(
+                "run.sh",
+                "echo $#\nn=${#arr[@]}\necho hi # note\n  # indented"
+            ),
+            vec![
+                LineKind::Code,
+                LineKind::Code,
+                LineKind::Mixed,
+                LineKind::Comment
+            ]
+        );
+        assert_eq!(
+            classify_all("guide.rst", "Wait... this\n.. note:: hidden"),
+            vec![LineKind::Code, LineKind::Comment]
+        );
+        assert_eq!(
+            classify_all("notes.txt", "see http://example.com\n// aside"),
+            vec![LineKind::Code, LineKind::Comment]
+        );
+
+        // Languages without the rule still find markers anywhere.
+        assert_eq!(
+            classify_all("main.c", "int x;// note"),
+            vec![LineKind::Mixed]
+        );
+    }
+
+    #[test]
+    fn test_runs_of_plain_code() {
+        assert_eq!(
+            classify_all(
+                "lib.rs",
+                "let total = a + b;\nlet café = \"é\"; // note\nx / y /* z */"
+            ),
+            vec![LineKind::Code, LineKind::Mixed, LineKind::Mixed]
+        );
+    }
+
+    #[test]
+    fn test_shell_shifts_are_not_heredocs() {
+        let source = "echo $(( a << b ))\n(( x <<= 2 ))\nlet y=x<<n\n# comment";
+        assert_eq!(
+            classify_all("run.sh", source),
+            vec![
+                LineKind::Code,
+                LineKind::Code,
+                LineKind::Code,
+                LineKind::Comment
+            ]
+        );
+    }
+
+    #[test]
+    fn test_doc_comments() {
+        let source = "//! Crate docs\n/// Item docs\n//// banner\n// note\n/**\n * Block docs\n */\n/**/\nfn f() {} /// trailing";
+        assert_eq!(
+            classify_all("lib.rs", source),
+            vec![
+                LineKind::DocComment,
+                LineKind::DocComment,
+                LineKind::Comment,
+                LineKind::Comment,
+                LineKind::DocComment,
+                LineKind::DocComment,
+                LineKind::DocComment,
+                LineKind::Comment,
+                LineKind::Mixed
+            ]
+        );
+
+        assert_eq!(
+            classify_all("Util.java", "/** Javadoc */\n/* plain */\nclass Util {}"),
+            vec![LineKind::DocComment, LineKind::Comment, LineKind::Code]
+        );
+    }
+}
@@ -1,2 +1,16 @@
+pub mod attributes;
 pub mod counter;
+pub mod directories;
+pub mod embedded;
+pub mod encoding;
 pub mod file_types;
+pub mod filters;
+pub mod generated;
+pub mod lexer;
+pub mod notebook;
+pub mod registry;
+pub mod sniff;
+pub mod test_code;
+#[cfg(test)]
+mod test_support;
+pub mod vendored;
@@ -0,0 +1,166 @@
+use crate::stats::embedded::{self, Embedding, Region};
+use crate::stats::file_types::FileType;
+use crate::stats::registry::LanguageRegistry;
+use serde::Deserialize;
+
+/// The parts of a Jupyter notebook (nbformat 4) that hold source.
+/// Outputs are not deserialized at all, so they never reach the counts.
+#[derive(Debug, Deserialize)]
+struct Notebook {
+    #[serde(default)]
+    cells: Vec<Cell>,
+    #[serde(default)]
+    metadata: Metadata,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Metadata {
+    kernelspec: Option<KernelSpec>,
+    language_info: Option<LanguageInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KernelSpec {
+    language: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LanguageInfo {
+    name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Cell {
+    cell_type: String,
+    #[serde(default)]
+    source: Source,
+}
+
+/// Cell source, stored either as one string or as a list of lines.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Source {
+    Lines(Vec<String>),
+    Text(String),
+}
+
+impl Default for Source {
+    fn default() -> Self {
+        Source::Text(String::new())
+    }
+}
+
+impl Source {
+    fn into_text(self) -> String {
+        match self {
+            Source::Lines(lines) => lines.concat(),
+            Source::Text(text) => text,
+        }
+    }
+}
+
+/// Split a notebook into one region per cell and return the regions with the
+/// number of cells. Code cells count under the kernel's language (Python when
+/// the metadata does not say), markdown cells under Markdown with their fenced
+/// blocks split out, and raw cells as plain text. Content that is not a valid
+/// notebook is returned as a single region of the host type.
+pub fn split(
+    content: &str,
+    host: &FileType,
+    registry: &'static LanguageRegistry,
+) -> (Vec<Region>, usize) {
+    let Ok(notebook) = serde_json::from_str::<Notebook>(content) else {
+        let region = Region {
+            file_type: host.clone(),
+            content: content.to_string(),
+            embedded: false,
+        };
+        return (vec![region], 0);
+    };
+
+    let kernel = notebook
+        .metadata
+        .kernelspec
+        .and_then(|spec| spec.language)
+        .or_else(|| notebook.metadata.language_info.and_then(|info| info.name))
+        .unwrap_or_else(|| "python".to_string());
+    let code_type = registry
+        .lookup_name(&kernel)
+        .unwrap_or_else(|| registry.text());
+    let markdown_type = registry.lookup_name("markdown");
+
+    let cells = notebook.cells.len();
+    let mut regions = Vec::new();
+    for cell in notebook.cells {
+        let source = cell.source.into_text();
+        match (cell.cell_type.as_str(), &markdown_type) {
+            ("code", _) => regions.push(cell_region(code_type.clone(), source)),
+            ("markdown", Some(markdown)) => {
+                let mut parts = embedded::split(&source, markdown, Embedding::Markdown, registry);
+                for part in &mut parts {
+                    part.embedded = true;
+                }
+                regions.extend(parts);
+            }
+            _ => regions.push(cell_region(registry.text(), source)),
+        }
+    }
+
+    (regions, cells)
+}
+
+fn cell_region(file_type: FileType, content: String) -> Region {
+    Region {
+        file_type,
+        content,
+        embedded: true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_notebook_cells() {
+        let notebook = r##"{
+            "metadata": {"kernelspec": {"language": "R", "name": "ir"}},
+            "nbformat": 4,
+            "cells": [
+                {"cell_type": "markdown", "source": ["# Title\n", "Some text"]},
+                {"cell_type": "code", "source": ["# load\n", "x <- 1\n", "print(x)"],
+                 "outputs": [{"output_type": "display_data",
+                              "data": {"image/png": "iVBORw0KGgo...\n...\n"}}]},
+                {"cell_type": "code", "source": "y <- 2"}
+            ]
+        }"##;
+
+        let host = FileType::from_path(Path::new("analysis.ipynb"));
+        let (regions, cells) = split(notebook, &host, LanguageRegistry::builtin());
+
+        assert_eq!(cells, 3);
+        let summary: Vec<_> = regions
+            .iter()
+            .map(|region| {
+                (
+                    region.file_type.language.as_str(),
+                    region.content.lines().count(),
+                )
+            })
+            .collect();
+        assert_eq!(summary, vec![("Markdown", 2), ("R", 3), ("R", 1)]);
+        assert!(regions.iter().all(|region| region.embedded));
+    }
+
+    #[test]
+    fn test_invalid_notebook_falls_back_to_host() {
+        let host = FileType::from_path(Path::new("broken.ipynb"));
+        let (regions, cells) = split("{\"cells\": [", &host, LanguageRegistry::builtin());
+
+        assert_eq!(cells, 0);
+        assert_eq!(regions.len(), 1);
+        assert_eq!(regions[0].file_type.language, host.language);
+        assert!(!regions[0].embedded);
+    }
+}
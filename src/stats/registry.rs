@@ -0,0 +1,691 @@
+use crate::stats::embedded::Embedding;
+use crate::stats::file_types::FileType;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Language definitions shipped with the crate.
+const BUILTIN_LANGUAGES: &str = include_str!("languages.toml");
+
+/// One `[[language]]` entry of a languages file.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LanguageDef {
+    pub name: String,
+    /// Files of this language are counted but not analyzed line by line.
+    pub binary: bool,
+    pub extensions: Vec<String>,
+    /// Exact file names, or patterns with a single `*` such as `Dockerfile.*`.
+    pub filenames: Vec<String>,
+    /// Extra names accepted in modelines and `.gitattributes` (`cpp`, `golang`).
+    pub aliases: Vec<String>,
+    /// Shebang interpreters, without version suffixes (`python`, `node`).
+    pub interpreters: Vec<String>,
+    pub line_comments: Vec<String>,
+    /// Line comments only start at the beginning of a line or after
+    /// whitespace, so `$#` in shell or a URL in plain text stays code.
+    pub spaced_line_comments: bool,
+    pub block_comments: Vec<[String; 2]>,
+    /// Prefixes of comments that are documentation: `///`, `/**`, `"""`.
+    pub doc_comments: Vec<String>,
+    pub nested_comments: bool,
+    /// Line prefixes that start a block of test code inside a file: `#[cfg(test)]`.
+    pub test_markers: Vec<String>,
+    pub string_delimiters: Vec<[String; 2]>,
+    pub verbatim_strings: Vec<[String; 2]>,
+    /// Openers of strings that may span lines (`"""`, a backtick); any other
+    /// string ends with its line unless the line ends in a `\` continuation.
+    pub multiline_strings: Vec<String>,
+    /// Strings only open where a value starts, as in YAML, so apostrophes in
+    /// plain scalars (`name: it's here`) are not quotes.
+    pub quoted_values: bool,
+    pub raw_strings: bool,
+    pub heredocs: bool,
+    /// Whether files contain regions in other languages (`html`, `markdown`).
+    pub embeds: Option<Embedding>,
+}
+
+/// Content rules choosing between languages that share an extension,
+/// like GitHub linguist's disambiguations. Rules are tried in order.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Disambiguation {
+    pub extension: String,
+    #[serde(rename = "rule")]
+    pub rules: Vec<HeuristicRule>,
+}
+
+/// One disambiguation rule: the language it picks and the snippets that
+/// identify it. A pattern starting with `^` must begin a line (after
+/// indentation) and one in `^...$` must be the whole line; others may appear
+/// anywhere. A rule without patterns always matches.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HeuristicRule {
+    pub language: String,
+    #[serde(default)]
+    pub patterns: Vec<String>,
+    #[serde(default)]
+    pub ignore_case: bool,
+}
+
+/// Outcome of a disambiguation, kept so verbose output can explain it.
+#[derive(Debug, Clone)]
+pub struct Disambiguated {
+    pub file_type: FileType,
+    pub extension: &'static str,
+    /// The pattern that matched, or `None` for a catch-all rule.
+    pub pattern: Option<&'static str>,
+}
+
+impl fmt::Display for Disambiguated {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.pattern {
+            Some(pattern) => write!(
+                f,
+                ".{} -> {} (found `{}`)",
+                self.extension, self.file_type.language, pattern
+            ),
+            None => write!(
+                f,
+                ".{} -> {} (default rule)",
+                self.extension, self.file_type.language
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LanguagesFile {
+    #[serde(default)]
+    language: Vec<LanguageDef>,
+    #[serde(default)]
+    disambiguation: Vec<Disambiguation>,
+}
+
+/// Lookup tables over a set of language definitions.
+/// When two definitions claim the same extension or file name, the later one wins.
+#[derive(Debug, Default)]
+pub struct LanguageRegistry {
+    languages: Vec<LanguageDef>,
+    extensions: HashMap<String, usize>,
+    file_names: HashMap<String, usize>,
+    file_name_globs: Vec<(String, usize)>,
+    names: HashMap<String, usize>,
+    interpreters: HashMap<String, usize>,
+    disambiguations: HashMap<String, Disambiguation>,
+}
+
+impl LanguageRegistry {
+    /// The built-in definitions, parsed once per process.
+    pub fn builtin() -> &'static LanguageRegistry {
+        static BUILTIN: OnceLock<LanguageRegistry> = OnceLock::new();
+        BUILTIN.get_or_init(|| {
+            Self::parse(BUILTIN_LANGUAGES).expect("built-in language definitions are valid")
+        })
+    }
+
+    /// Registry for a scan: the built-ins, extended by a user languages file if given.
+    /// Entries in the file add languages or replace built-ins with the same name.
+    /// A custom registry is leaked so `FileType`s can borrow from it for the rest of the process.
+    pub fn load(
+        path: Option<&Path>,
+    ) -> Result<&'static LanguageRegistry, Box<dyn std::error::Error>> {
+        let Some(path) = path else {
+            return Ok(Self::builtin());
+        };
+
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("cannot read language definitions {}: {}", path.display(), e))?;
+        let invalid = |e| format!("invalid language definitions in {}: {}", path.display(), e);
+        let definitions = parse_definitions(&content).map_err(invalid)?;
+        let registry = Self::builtin().with_definitions(definitions);
+        registry.check_disambiguations().map_err(invalid)?;
+
+        Ok(Box::leak(Box::new(registry)))
+    }
+
+    fn parse(content: &str) -> Result<Self, String> {
+        let definitions = parse_definitions(content)?;
+        let registry = Self::from_definitions(definitions.language, definitions.disambiguation);
+        registry.check_disambiguations()?;
+        Ok(registry)
+    }
+
+    /// Copy of this registry with the languages and disambiguations of a
+    /// languages file added, replacing same-named languages and rules for the same extension.
+    fn with_definitions(&self, definitions: LanguagesFile) -> Self {
+        let mut languages = self.languages.clone();
+        for definition in definitions.language {
+            languages.retain(|existing| !existing.name.eq_ignore_ascii_case(&definition.name));
+            languages.push(definition);
+        }
+
+        let mut disambiguations: Vec<_> = self.disambiguations.values().cloned().collect();
+        disambiguations.extend(definitions.disambiguation);
+
+        Self::from_definitions(languages, disambiguations)
+    }
+
+    fn from_definitions(languages: Vec<LanguageDef>, disambiguations: Vec<Disambiguation>) -> Self {
+        let mut registry = Self::default();
+
+        for disambiguation in disambiguations {
+            let extension = disambiguation
+                .extension
+                .trim_start_matches('.')
+                .to_lowercase();
+            registry.disambiguations.insert(extension, disambiguation);
+        }
+
+        for (index, language) in languages.iter().enumerate() {
+            registry.names.insert(language.name.to_lowercase(), index);
+            for alias in &language.aliases {
+                registry.names.insert(alias.to_lowercase(), index);
+            }
+            for extension in &language.extensions {
+                registry.extensions.insert(extension.to_lowercase(), index);
+            }
+            for file_name in &language.filenames {
+                if file_name.contains('*') {
+                    registry.file_name_globs.push((file_name.clone(), index));
+                } else {
+                    registry.file_names.insert(file_name.clone(), index);
+                }
+            }
+            for interpreter in &language.interpreters {
+                registry.interpreters.insert(interpreter.clone(), index);
+            }
+        }
+
+        registry.languages = languages;
+        registry
+    }
+
+    /// Every disambiguation rule must name a known language.
+    fn check_disambiguations(&self) -> Result<(), String> {
+        for (extension, disambiguation) in &self.disambiguations {
+            for rule in &disambiguation.rules {
+                if self.by_name(&rule.language).is_none() {
+                    return Err(format!(
+                        "disambiguation for .{} names unknown language '{}'",
+                        extension, rule.language
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// All definitions, in lookup order.
+    pub fn languages(&self) -> &[LanguageDef] {
+        &self.languages
+    }
+
+    /// Generic text type used when nothing else matches.
+    pub fn text(&'static self) -> FileType {
+        // Custom definitions can replace "Text" but never remove it
+        let text = self.by_name("Text").expect("Text is always defined");
+        FileType::from_definition(text)
+    }
+
+    /// Detect file type from a path: well-known file names first, then the extension.
+    pub fn detect_path(&'static self, path: &Path) -> FileType {
+        if let Some(file_type) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| self.detect_file_name(name))
+        {
+            return file_type;
+        }
+
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+
+        self.detect_extension(&extension)
+    }
+
+    /// File type for a lowercase extension, or plain text when none matches.
+    pub fn detect_extension(&'static self, extension: &str) -> FileType {
+        match self.extensions.get(extension) {
+            Some(&index) => FileType::from_definition(&self.languages[index]),
+            None => self.text(),
+        }
+    }
+
+    /// File type for a well-known file name; exact names win over patterns.
+    pub fn detect_file_name(&'static self, name: &str) -> Option<FileType> {
+        let index = self.file_names.get(name).copied().or_else(|| {
+            self.file_name_globs
+                .iter()
+                .rev()
+                .find(|(pattern, _)| glob_matches(pattern, name))
+                .map(|(_, index)| *index)
+        })?;
+
+        Some(FileType::from_definition(&self.languages[index]))
+    }
+
+    /// File type for a language name or alias, case-insensitively.
+    /// Known extensions are accepted too, so `ft=rb` resolves to Ruby.
+    /// Binary categories and the plain text fallback are not languages.
+    pub fn lookup_name(&'static self, name: &str) -> Option<FileType> {
+        let name = name.trim().to_lowercase();
+        let name = name.strip_suffix("-mode").unwrap_or(&name);
+
+        self.by_name(name)
+            .or_else(|| {
+                self.extensions
+                    .get(name)
+                    .map(|&index| &self.languages[index])
+            })
+            .filter(|language| !language.binary && language.name != "Text")
+            .map(FileType::from_definition)
+    }
+
+    /// Canonical name of any definition, the binary categories and the plain
+    /// text fallback included, for filters that may select those too.
+    pub fn definition_name(&self, name: &str) -> Option<&str> {
+        self.by_name(name.trim())
+            .map(|language| language.name.as_str())
+    }
+
+    /// Detect the language of a script from its `#!` line.
+    /// Handles `env` (including `env -S` and variable assignments) and strips
+    /// interpreter versions, so `python3.11` and `node18` map like `python` and `node`.
+    pub fn detect_shebang(&'static self, first_line: &str) -> Option<FileType> {
+        let command = first_line.strip_prefix("#!")?;
+        let mut words = command
+            .split_whitespace()
+            .map(|word| word.trim_matches(|c| c == '"' || c == '\''));
+
+        let mut interpreter = program_name(words.next()?);
+        if interpreter == "env" {
+            interpreter = loop {
+                let word = words.next()?;
+                match word {
+                    // Options that take a separate argument
+                    "-u" | "--unset" | "-C" | "--chdir" => {
+                        words.next();
+                    }
+                    // `-S` splits the rest of the line into arguments
+                    "-S" | "--split-string" => {}
+                    _ if word.starts_with("-S") => break program_name(&word[2..]),
+                    _ if word.starts_with('-') || word.contains('=') => {}
+                    _ => break program_name(word),
+                }
+            };
+        }
+
+        let index = *self.interpreters.get(strip_version(interpreter))?;
+        Some(FileType::from_definition(&self.languages[index]))
+    }
+
+    /// Detect a language declared by an editor modeline in the first or last
+    /// five lines: vim `vim: set ft=ruby:` / `vi: filetype=sh` or Emacs
+    /// `-*- mode: lisp -*-` / `-*- python -*-`.
+    pub fn detect_modeline(&'static self, content: &str) -> Option<FileType> {
+        let lines: Vec<&str> = content.lines().collect();
+        let head = lines.iter().take(5);
+        let tail = lines.iter().skip(lines.len().saturating_sub(5).max(5));
+
+        head.chain(tail)
+            .find_map(|line| vim_modeline(line).or_else(|| emacs_modeline(line)))
+            .and_then(|name| self.lookup_name(name))
+    }
+
+    /// Languages `disambiguate` may pick for a path, before reading it.
+    pub fn disambiguation_languages(&'static self, path: &Path) -> Vec<&'static str> {
+        let disambiguation = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .filter(|name| self.detect_file_name(name).is_none())
+            .and(path.extension())
+            .and_then(|extension| extension.to_str())
+            .and_then(|extension| self.disambiguations.get(&extension.to_lowercase()));
+
+        disambiguation
+            .map(|disambiguation| {
+                disambiguation
+                    .rules
+                    .iter()
+                    .map(|rule| rule.language.as_str())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Pick a language from the content of a file whose extension is shared
+    /// by several languages. Files recognised by their name are left alone,
+    /// as are files no rule matches.
+    pub fn disambiguate(&'static self, path: &Path, content: &str) -> Option<Disambiguated> {
+        let name = path.file_name()?.to_str()?;
+        if self.detect_file_name(name).is_some() {
+            return None;
+        }
+
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        let (extension, disambiguation) = self.disambiguations.get_key_value(&extension)?;
+
+        let lowered = disambiguation
+            .rules
+            .iter()
+            .any(|rule| rule.ignore_case)
+            .then(|| content.to_lowercase());
+
+        disambiguation.rules.iter().find_map(|rule| {
+            let text = match (&lowered, rule.ignore_case) {
+                (Some(lowered), true) => lowered.as_str(),
+                _ => content,
+            };
+            let pattern = if rule.patterns.is_empty() {
+                None
+            } else {
+                let pattern = rule
+                    .patterns
+                    .iter()
+                    .find(|pattern| pattern_matches(pattern, text))?;
+                Some(pattern.as_str())
+            };
+
+            Some(Disambiguated {
+                file_type: FileType::from_definition(self.by_name(&rule.language)?),
+                extension,
+                pattern,
+            })
+        })
+    }
+
+    fn by_name(&self, name: &str) -> Option<&LanguageDef> {
+        self.names
+            .get(&name.to_lowercase())
+            .map(|&index| &self.languages[index])
+    }
+}
+
+/// Parse and validate the `[[language]]` entries of a languages file.
+fn parse_definitions(content: &str) -> Result<LanguagesFile, String> {
+    let mut file: LanguagesFile = toml::from_str(content).map_err(|e| e.to_string())?;
+
+    for (position, language) in file.language.iter().enumerate() {
+        if language.name.trim().is_empty() {
+            return Err(format!("language entry {} has no name", position + 1));
+        }
+
+        let tokens = language
+            .line_comments
+            .iter()
+            .chain(language.block_comments.iter().flatten())
+            .chain(language.doc_comments.iter())
+            .chain(language.test_markers.iter())
+            .chain(language.string_delimiters.iter().flatten())
+            .chain(language.verbatim_strings.iter().flatten())
+            .chain(language.multiline_strings.iter());
+        for token in tokens {
+            if token.is_empty() {
+                return Err(format!(
+                    "language '{}' has an empty comment or string delimiter",
+                    language.name
+                ));
+            }
+        }
+    }
+
+    for disambiguation in &mut file.disambiguation {
+        for rule in &mut disambiguation.rules {
+            if rule
+                .patterns
+                .iter()
+                .any(|pattern| match pattern.strip_prefix('^') {
+                    Some(anchored) => anchored.trim_end_matches('$').is_empty(),
+                    None => pattern.is_empty(),
+                })
+            {
+                return Err(format!(
+                    "disambiguation for .{} has an empty pattern",
+                    disambiguation.extension
+                ));
+            }
+            if rule.ignore_case {
+                for pattern in &mut rule.patterns {
+                    *pattern = pattern.to_lowercase();
+                }
+            }
+        }
+    }
+
+    Ok(file)
+}
+
+/// Whether a disambiguation pattern occurs in `content`; a leading `^`
+/// anchors it to the start of a line, ignoring indentation, and `^...$` to
+/// the whole line, ignoring surrounding whitespace.
+fn pattern_matches(pattern: &str, content: &str) -> bool {
+    let Some(prefix) = pattern.strip_prefix('^') else {
+        return content.contains(pattern);
+    };
+
+    match prefix.strip_suffix('$') {
+        Some(whole) => content.lines().any(|line| line.trim() == whole),
+        None => content
+            .lines()
+            .any(|line| line.trim_start().starts_with(prefix)),
+    }
+}
+
+/// Match a file name against a pattern containing at most one `*`.
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    match pattern.split_once('*') {
+        Some((prefix, suffix)) => {
+            name.len() >= prefix.len() + suffix.len()
+                && name.starts_with(prefix)
+                && name.ends_with(suffix)
+        }
+        None => pattern == name,
+    }
+}
+
+/// Final path component of an interpreter path such as `/usr/bin/python3`.
+fn program_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// Strip a trailing version from an interpreter name: `python3.11` -> `python`.
+fn strip_version(name: &str) -> &str {
+    name.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.' || c == '-')
+}
+
+/// Language named by a vim modeline: `vim: set ft=ruby:` or `vi: filetype=sh`.
+fn vim_modeline(line: &str) -> Option<&str> {
+    let start = ["vim:", "vi:", "ex:"].iter().find_map(|marker| {
+        line.match_indices(marker)
+            .find(|(i, _)| *i == 0 || line[..*i].ends_with(char::is_whitespace))
+            .map(|(i, _)| i + marker.len())
+    })?;
+
+    line[start..]
+        .split(|c: char| c == ':' || c.is_whitespace())
+        .find_map(|option| {
+            let (key, value) = option.split_once('=')?;
+            matches!(key, "ft" | "filetype" | "syn" | "syntax").then_some(value)
+        })
+        .filter(|value| !value.is_empty())
+}
+
+/// Language named by an Emacs modeline: `-*- mode: lisp -*-` or `-*- python -*-`.
+fn emacs_modeline(line: &str) -> Option<&str> {
+    let (_, rest) = line.split_once("-*-")?;
+    let (body, _) = rest.split_once("-*-")?;
+
+    if !body.contains(':') {
+        return Some(body.trim()).filter(|mode| !mode.is_empty());
+    }
+
+    body.split(';').find_map(|variable| {
+        let (key, value) = variable.split_once(':')?;
+        (key.trim().eq_ignore_ascii_case("mode")).then(|| value.trim())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leak(registry: LanguageRegistry) -> &'static LanguageRegistry {
+        Box::leak(Box::new(registry))
+    }
+
+    #[test]
+    fn test_builtin_definitions_parse() {
+        let registry = LanguageRegistry::builtin();
+        assert!(registry.languages().len() > 30);
+        assert_eq!(registry.detect_extension("rs").language, "Rust");
+        assert_eq!(registry.detect_extension("nope").language, "Text");
+        assert!(!glob_matches("Dockerfile.*", "Dockerfile"));
+    }
+
+    #[test]
+    fn test_user_definitions_add_and_override() {
+        let user = r##"
+            [[language]]
+            name = "Zig"
+            extensions = ["zig"]
+            line_comments = ["//"]
+
+            [[language]]
+            name = "php"
+            extensions = ["php", "inc"]
+            line_comments = ["#"]
+        "##;
+        let definitions = parse_definitions(user).unwrap();
+        let registry = leak(LanguageRegistry::builtin().with_definitions(definitions));
+
+        let zig = registry.detect_extension("zig");
+        assert_eq!(zig.language, "Zig");
+        assert_eq!(zig.comment_patterns, vec!["//"]);
+
+        let php = registry.detect_extension("inc");
+        assert_eq!(php.language, "php");
+        assert_eq!(php.comment_patterns, vec!["#"]);
+        assert_eq!(registry.detect_extension("php").language, "php");
+
+        // Untouched built-ins stay available
+        assert_eq!(registry.detect_extension("rs").language, "Rust");
+    }
+
+    #[test]
+    fn test_malformed_definitions_are_rejected() {
+        let unknown_field = "[[language]]\nname = \"X\"\ncomments = [\"#\"]\n";
+        assert!(parse_definitions(unknown_field)
+            .unwrap_err()
+            .contains("comments"));
+
+        let bad_pair = "[[language]]\nname = \"X\"\nblock_comments = [[\"/*\"]]\n";
+        assert!(parse_definitions(bad_pair).is_err());
+
+        let no_name = "[[language]]\nextensions = [\"x\"]\n";
+        assert_eq!(
+            parse_definitions(no_name).unwrap_err(),
+            "language entry 1 has no name"
+        );
+
+        let empty_token = "[[language]]\nname = \"X\"\nline_comments = [\"\"]\n";
+        assert!(parse_definitions(empty_token)
+            .unwrap_err()
+            .contains("empty"));
+
+        assert!(LanguageRegistry::load(Some(Path::new("/nonexistent/langs.toml"))).is_err());
+    }
+
+    #[test]
+    fn test_disambiguation_rules() {
+        let registry = LanguageRegistry::builtin();
+        let language = |name: &str, content: &str| {
+            registry
+                .disambiguate(Path::new(name), content)
+                .map(|found| found.file_type.language)
+        };
+
+        assert_eq!(
+            language("widget.h", "namespace ui {\nclass Widget;\n}\n"),
+            Some("C++".to_string())
+        );
+        assert_eq!(
+            language("View.h", "#import <UIKit/UIKit.h>\n@interface View\n@end\n"),
+            Some("Objective-C".to_string())
+        );
+        assert_eq!(
+            language("util.h", "int add(int a, int b);\n"),
+            Some("C".to_string())
+        );
+        assert_eq!(
+            language("solve.m", "% solve\nfunction x = solve(a)\nend\n"),
+            Some("MATLAB".to_string())
+        );
+        assert_eq!(
+            language(
+                "family.pl",
+                "parent(tom, bob).\nchild(X) :- parent(_, X).\n"
+            ),
+            Some("Prolog".to_string())
+        );
+        assert_eq!(language("tool.pl", "print 1;\n"), None);
+        assert_eq!(
+            language("script.r", "REBOL [Title: \"x\"]\n"),
+            Some("Rebol".to_string())
+        );
+        assert_eq!(
+            language("proc.sql", "CREATE PROCEDURE p AS\nSET NOCOUNT ON;\nGO\n"),
+            Some("T-SQL".to_string())
+        );
+        assert_eq!(
+            language("batch.sql", "SELECT 1\n  go  \nSELECT good FROM t\n"),
+            Some("T-SQL".to_string())
+        );
+        assert_eq!(
+            language(
+                "count.sql",
+                "CREATE FUNCTION n() RETURNS int AS $$\nSELECT 1;\n$$ LANGUAGE sql;\n"
+            ),
+            Some("PL/pgSQL".to_string())
+        );
+        // MySQL delimiters and system variables are neither PL/pgSQL nor T-SQL
+        let mysql = "DELIMITER $$\nCREATE PROCEDURE p()\nBEGIN\n  SELECT @@session.sql_mode, @@IDENTITY;\nEND$$\nDELIMITER ;\n";
+        assert_eq!(language("proc.sql", mysql), Some("SQL".to_string()));
+        assert_eq!(
+            language(
+                "app_de.ts",
+                "<?xml version=\"1.0\"?>\n<TS version=\"2.1\">\n"
+            ),
+            Some("XML".to_string())
+        );
+        assert_eq!(
+            language("app.ts", "export const x = 1;\n"),
+            Some("TypeScript".to_string())
+        );
+        assert_eq!(language("main.rs", "namespace"), None);
+
+        let found = registry
+            .disambiguate(Path::new("a.h"), "template <class T> T id(T);\n")
+            .unwrap();
+        assert_eq!(found.to_string(), ".h -> C++ (found `^template`)");
+    }
+
+    #[test]
+    fn test_disambiguation_needs_known_languages() {
+        let user = "[[disambiguation]]\nextension = 'inc'\n\n[[disambiguation.rule]]\nlanguage = 'Klingon'\n";
+        let registry =
+            LanguageRegistry::builtin().with_definitions(parse_definitions(user).unwrap());
+        assert_eq!(
+            registry.check_disambiguations().unwrap_err(),
+            "disambiguation for .inc names unknown language 'Klingon'"
+        );
+    }
+}
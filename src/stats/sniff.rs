@@ -0,0 +1,140 @@
+/// Number of bytes read from the start of a file to decide whether it is binary.
+pub const SNIFF_LEN: usize = 8 * 1024;
+
+/// Share of control bytes above which content is considered binary.
+const CONTROL_RATIO: f64 = 0.1;
+
+/// File signatures and the binary category they belong to.
+const MAGIC_NUMBERS: &[(&[u8], &str)] = &[
+    (b"\x7fELF", "Binary"),
+    (b"\xfe\xed\xfa\xce", "Binary"),
+    (b"\xfe\xed\xfa\xcf", "Binary"),
+    (b"\xce\xfa\xed\xfe", "Binary"),
+    (b"\xcf\xfa\xed\xfe", "Binary"),
+    (b"\xca\xfe\xba\xbe", "Binary"),
+    (b"\0asm", "Binary"),
+    (b"SQLite format 3\0", "Binary"),
+    (b"\x89PNG\r\n\x1a\n", "Image"),
+    (b"\xff\xd8\xff", "Image"),
+    (b"GIF87a", "Image"),
+    (b"GIF89a", "Image"),
+    (b"PK\x03\x04", "Archive"),
+    (b"PK\x05\x06", "Archive"),
+    (b"\x1f\x8b", "Archive"),
+    (b"\xfd7zXZ\0", "Archive"),
+    (b"7z\xbc\xaf\x27\x1c", "Archive"),
+    (b"%PDF-", "Document"),
+];
+
+/// Offset of `e_lfanew`, the pointer from a DOS `MZ` header to the PE header.
+/// `MZ` alone is printable and starts ordinary text, so the PE signature it
+/// points to is checked too.
+const PE_POINTER_OFFSET: usize = 0x3c;
+
+/// MPEG transport streams have no magic number, but every 188-byte packet
+/// starts with this sync byte.
+const TS_SYNC_BYTE: u8 = 0x47;
+const TS_PACKET_LEN: usize = 188;
+
+/// Inspect the first bytes of a file and return its binary category, if any.
+/// Checks magic numbers first, then NUL bytes and the ratio of control characters.
+pub fn binary_kind(head: &[u8]) -> Option<&'static str> {
+    if let Some((_, kind)) = MAGIC_NUMBERS
+        .iter()
+        .find(|(magic, _)| head.starts_with(magic))
+    {
+        return Some(kind);
+    }
+
+    if is_pe_executable(head) {
+        return Some("Binary");
+    }
+
+    if is_transport_stream(head) {
+        return Some("Video");
+    }
+
+    if head.contains(&0) {
+        return Some("Binary");
+    }
+
+    let control = head.iter().filter(|&&b| is_control(b)).count();
+    if !head.is_empty() && control as f64 / head.len() as f64 > CONTROL_RATIO {
+        return Some("Binary");
+    }
+
+    None
+}
+
+/// Whether the head is a Windows executable: `MZ` with `e_lfanew` pointing
+/// at a `PE\0\0` signature.
+fn is_pe_executable(head: &[u8]) -> bool {
+    if !head.starts_with(b"MZ") {
+        return false;
+    }
+
+    let Some(pointer) = head.get(PE_POINTER_OFFSET..PE_POINTER_OFFSET + 4) else {
+        return false;
+    };
+    let offset = u32::from_le_bytes([pointer[0], pointer[1], pointer[2], pointer[3]]) as usize;
+    head.get(offset..offset.saturating_add(4)) == Some(b"PE\0\0")
+}
+
+/// Whether the head looks like consecutive MPEG-TS packets.
+fn is_transport_stream(head: &[u8]) -> bool {
+    head.len() >= TS_PACKET_LEN * 2
+        && head
+            .iter()
+            .step_by(TS_PACKET_LEN)
+            .all(|&byte| byte == TS_SYNC_BYTE)
+}
+
+/// Control bytes that do not normally appear in text (tabs, newlines,
+/// form feeds and ANSI escapes are fine).
+fn is_control(byte: u8) -> bool {
+    matches!(byte, 0x01..=0x08 | 0x0e..=0x1a | 0x1c..=0x1f | 0x7f)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_magic_numbers() {
+        assert_eq!(binary_kind(b"\x7fELF\x02\x01\x01"), Some("Binary"));
+        assert_eq!(binary_kind(b"\x89PNG\r\n\x1a\n\0\0"), Some("Image"));
+        assert_eq!(binary_kind(b"PK\x03\x04\x14\0"), Some("Archive"));
+        assert_eq!(binary_kind(b"%PDF-1.7\n"), Some("Document"));
+
+        let mut exe = vec![0u8; 0x84];
+        exe[..2].copy_from_slice(b"MZ");
+        exe[PE_POINTER_OFFSET] = 0x80;
+        exe[0x80..].copy_from_slice(b"PE\0\0");
+        assert!(is_pe_executable(&exe));
+        assert_eq!(binary_kind(&exe), Some("Binary"));
+
+        let mut stream = vec![0xffu8; TS_PACKET_LEN * 3];
+        for packet in stream.chunks_mut(TS_PACKET_LEN) {
+            packet[0] = TS_SYNC_BYTE;
+        }
+        assert_eq!(binary_kind(&stream), Some("Video"));
+    }
+
+    #[test]
+    fn test_text_content() {
+        assert_eq!(binary_kind(b""), None);
+        assert_eq!(binary_kind(b"fn main() {\n\tprintln!(\"hi\");\n}\n"), None);
+        assert_eq!(
+            binary_kind("caf\u{e9} \x1b[1mbold\x1b[0m\r\n".as_bytes()),
+            None
+        );
+        // A printable `MZ` prefix without a PE header is just text
+        assert_eq!(binary_kind(b"MZ_LIMIT = 5\nprint(MZ_LIMIT)\n"), None);
+    }
+
+    #[test]
+    fn test_nul_and_control_bytes() {
+        assert_eq!(binary_kind(b"text\0more"), Some("Binary"));
+        assert_eq!(binary_kind(b"\x01\x02\x03\x04abc"), Some("Binary"));
+    }
+}
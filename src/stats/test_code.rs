@@ -0,0 +1,116 @@
+/// Paths that hold tests by convention. Directory patterns match at any depth.
+pub const DEFAULT_TEST_PATTERNS: &[&str] = &[
+    "tests/",
+    "test/",
+    "__tests__/",
+    "spec/",
+    "*_test.go",
+    "test_*.py",
+    "*_test.py",
+    "*.spec.ts",
+    "*.test.ts",
+    "*.spec.tsx",
+    "*.test.tsx",
+    "*.spec.js",
+    "*.test.js",
+    "*.spec.jsx",
+    "*.test.jsx",
+    "*_spec.rb",
+    "*Test.java",
+    "*Tests.cs",
+];
+
+/// Follows test blocks inside a file, such as Rust's `#[cfg(test)] mod tests { ... }`.
+/// A block starts at a line beginning with one of the language's test markers
+/// and covers the item that follows: up to its closing brace, or up to a `;`
+/// when the item has no body. Braces are counted without regard to strings
+/// or comments, which is close enough for test modules.
+#[derive(Debug)]
+pub struct TestBlocks<'a> {
+    markers: &'a [&'static str],
+    block: Option<Block>,
+}
+
+#[derive(Debug)]
+struct Block {
+    depth: usize,
+    opened: bool,
+}
+
+impl<'a> TestBlocks<'a> {
+    pub fn new(markers: &'a [&'static str]) -> Self {
+        Self {
+            markers,
+            block: None,
+        }
+    }
+
+    /// Whether a line is part of a test block; lines must be passed in order.
+    pub fn is_test_line(&mut self, line: &str) -> bool {
+        let rest = match self.block {
+            Some(_) => line,
+            None => {
+                let trimmed = line.trim_start();
+                let Some(marker) = self
+                    .markers
+                    .iter()
+                    .find(|&&marker| trimmed.starts_with(marker))
+                else {
+                    return false;
+                };
+                self.block = Some(Block {
+                    depth: 0,
+                    opened: false,
+                });
+                &trimmed[marker.len()..]
+            }
+        };
+
+        if let Some(block) = &mut self.block {
+            for c in rest.chars() {
+                match c {
+                    '{' => {
+                        block.depth += 1;
+                        block.opened = true;
+                    }
+                    '}' => block.depth = block.depth.saturating_sub(1),
+                    ';' if !block.opened => block.opened = true,
+                    _ => {}
+                }
+                if block.opened && block.depth == 0 {
+                    self.block = None;
+                    break;
+                }
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_lines(source: &str) -> Vec<bool> {
+        let mut blocks = TestBlocks::new(&["#[cfg(test)]"]);
+        source
+            .lines()
+            .map(|line| blocks.is_test_line(line))
+            .collect()
+    }
+
+    #[test]
+    fn test_rust_test_module() {
+        let source = "fn add() {}\n\n#[cfg(test)]\nmod tests {\n    #[test]\n    fn it() {\n        add();\n    }\n}\nfn after() {}";
+        assert_eq!(
+            test_lines(source),
+            vec![false, false, true, true, true, true, true, true, true, false]
+        );
+    }
+
+    #[test]
+    fn test_items_without_body() {
+        let source = "#[cfg(test)]\nuse std::fs;\nfn main() {}\n#[cfg(test)] mod helpers { fn h() {} }\nfn end() {}";
+        assert_eq!(test_lines(source), vec![true, true, false, true, false]);
+    }
+}
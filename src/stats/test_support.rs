@@ -0,0 +1,11 @@
+use std::fs;
+use std::path::PathBuf;
+
+/// Empty scratch directory under the system temp dir, unique per test
+/// name and process.
+pub fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("tallyhawk-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
@@ -0,0 +1,58 @@
+use crate::stats::counter::{FileTypeStats, ProjectStats};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Directories holding third-party code. `node_modules` only shows up in a
+/// scan when it is not gitignored.
+pub const DEFAULT_VENDORED_PATTERNS: &[&str] =
+    &["vendor/", "third_party/", "node_modules/", "external/"];
+
+/// Counts for vendored and third-party code, kept out of the project totals.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct VendoredStats {
+    /// The path patterns that marked code as vendored, besides `linguist-vendored`.
+    pub patterns: Vec<String>,
+    pub total_files: usize,
+    pub total_lines: usize,
+    pub total_code_lines: usize,
+    pub total_comment_lines: usize,
+    pub total_doc_comment_lines: usize,
+    pub total_blank_lines: usize,
+    pub total_size_bytes: u64,
+    pub file_types: HashMap<String, FileTypeStats>,
+}
+
+impl VendoredStats {
+    /// Fold the counts of a scan of vendored files into these.
+    pub fn add(&mut self, stats: ProjectStats) {
+        self.merge(VendoredStats {
+            patterns: Vec::new(),
+            total_files: stats.total_files,
+            total_lines: stats.total_lines,
+            total_code_lines: stats.total_code_lines,
+            total_comment_lines: stats.total_comment_lines,
+            total_doc_comment_lines: stats.total_doc_comment_lines,
+            total_blank_lines: stats.total_blank_lines,
+            total_size_bytes: stats.total_size_bytes,
+            file_types: stats.file_types,
+        });
+    }
+
+    /// Fold another set of vendored counts into these; the patterns are kept.
+    pub fn merge(&mut self, other: VendoredStats) {
+        self.total_files += other.total_files;
+        self.total_lines += other.total_lines;
+        self.total_code_lines += other.total_code_lines;
+        self.total_comment_lines += other.total_comment_lines;
+        self.total_doc_comment_lines += other.total_doc_comment_lines;
+        self.total_blank_lines += other.total_blank_lines;
+        self.total_size_bytes += other.total_size_bytes;
+
+        for (language, file_stats) in other.file_types {
+            self.file_types
+                .entry(language)
+                .or_default()
+                .merge(file_stats);
+        }
+    }
+}
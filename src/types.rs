@@ -1,3 +1,5 @@
+use serde::{Deserialize, Serialize};
+
 /// Output format options for displaying results
 #[derive(clap::ValueEnum, Clone, Debug, PartialEq)]
 pub enum OutputFormat {
@@ -5,3 +7,139 @@ pub enum OutputFormat {
     Json,
     Csv,
 }
+
+/// How lines holding both code and a trailing comment are counted.
+/// They are always reported in the mixed column; the policy decides whether
+/// they also count towards code lines, comment lines or both.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum MixedPolicy {
+    #[default]
+    Code,
+    Comment,
+    Both,
+}
+
+/// Codepage used for files that are neither UTF-8 nor UTF-16.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum FallbackEncoding {
+    #[default]
+    Latin1,
+    Windows1252,
+}
+
+/// Order of the per-file list from `--by-file`. Counts sort largest first,
+/// paths alphabetically; ties fall back to the path.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum FileSort {
+    #[default]
+    Lines,
+    Code,
+    Comments,
+    Blanks,
+    Size,
+    Path,
+}
+
+/// Which ignore files the walk applies. Hidden files are controlled
+/// separately, by `--all`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IgnorePolicy {
+    /// `.gitignore` files and `.git/info/exclude`.
+    pub gitignore: bool,
+    /// The user's global gitignore (`core.excludesFile`).
+    pub global_gitignore: bool,
+    /// `.ignore` files.
+    pub ignore_files: bool,
+    /// `.tallyhawkignore` files.
+    pub tallyhawkignore: bool,
+    /// Ignore files in the directories above the scan root.
+    pub parents: bool,
+}
+
+impl Default for IgnorePolicy {
+    fn default() -> Self {
+        Self {
+            gitignore: true,
+            global_gitignore: true,
+            ignore_files: true,
+            tallyhawkignore: true,
+            parents: true,
+        }
+    }
+}
+
+impl IgnorePolicy {
+    /// Apply no ignore files at all.
+    pub fn none() -> Self {
+        Self {
+            gitignore: false,
+            global_gitignore: false,
+            ignore_files: false,
+            tallyhawkignore: false,
+            parents: false,
+        }
+    }
+}
+
+/// Extension override given on the command line as `--map ext=Language`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExtensionMapping {
+    /// Lowercase extension without the leading dot.
+    pub extension: String,
+    pub target: MappingTarget,
+}
+
+/// What files with a mapped extension are treated as.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MappingTarget {
+    /// A language name or alias known to the language registry.
+    Language(String),
+    /// Counted as binary without reading the content.
+    Binary,
+    /// Left out of the scan entirely.
+    Ignore,
+}
+
+impl std::str::FromStr for ExtensionMapping {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (extension, target) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected EXT=LANGUAGE, got '{}'", s))?;
+
+        let extension = extension.trim().trim_start_matches('.').to_lowercase();
+        let target = target.trim();
+        if extension.is_empty() || target.is_empty() {
+            return Err(format!("expected EXT=LANGUAGE, got '{}'", s));
+        }
+
+        let target = match target.to_lowercase().as_str() {
+            "binary" => MappingTarget::Binary,
+            "ignore" => MappingTarget::Ignore,
+            _ => MappingTarget::Language(target.to_string()),
+        };
+
+        Ok(ExtensionMapping { extension, target })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_extension_mapping() {
+        let mapping: ExtensionMapping = ".INC=PHP".parse().unwrap();
+        assert_eq!(mapping.extension, "inc");
+        assert_eq!(mapping.target, MappingTarget::Language("PHP".to_string()));
+
+        let ignored: ExtensionMapping = "dat=ignore".parse().unwrap();
+        assert_eq!(ignored.target, MappingTarget::Ignore);
+        let binary: ExtensionMapping = "blob=Binary".parse().unwrap();
+        assert_eq!(binary.target, MappingTarget::Binary);
+
+        assert!("inc".parse::<ExtensionMapping>().is_err());
+        assert!("=PHP".parse::<ExtensionMapping>().is_err());
+    }
+}
@@ -1,4 +1,5 @@
 use crate::stats::counter::ProjectStats;
+use crate::stats::directories::DirectoryStats;
 use crate::types::OutputFormat;
 use colored::*;
 
@@ -45,6 +46,39 @@ impl OutputFormatter {
             "Comment Lines:".bright_white(),
             stats.total_comment_lines.to_string().yellow().bold()
         );
+        println!(
+            "{}  {}",
+            "Doc Comment Lines:".bright_white(),
+            stats
+                .total_doc_comment_lines
+                .to_string()
+                .bright_yellow()
+                .bold()
+        );
+        println!(
+            "{}  {}",
+            "Mixed Lines:".bright_white(),
+            stats.total_mixed_lines.to_string().cyan().bold()
+        );
+        println!(
+            "{}  {}",
+            "Embedded Lines:".bright_white(),
+            stats.total_embedded_lines.to_string().bright_blue().bold()
+        );
+        println!(
+            "{}  {}",
+            "Generated Lines:".bright_white(),
+            stats
+                .total_generated_lines
+                .to_string()
+                .bright_black()
+                .bold()
+        );
+        println!(
+            "{}  {}",
+            "Test Lines:".bright_white(),
+            stats.total_test_lines.to_string().bright_green().bold()
+        );
         println!(
             "{}  {}",
             "Blank Lines:".bright_white(),
@@ -55,25 +89,39 @@ impl OutputFormatter {
             "Total Size:".bright_white(),
             format_bytes(stats.total_size_bytes).magenta().bold()
         );
+        if stats.total_duplicate_files > 0 {
+            println!(
+                "{}  {}",
+                "Duplicate Links Skipped:".bright_white(),
+                stats
+                    .total_duplicate_files
+                    .to_string()
+                    .bright_black()
+                    .bold()
+            );
+        }
 
         if !stats.file_types.is_empty() {
             println!("\n{}", "📁 File Types Breakdown".bold().yellow());
-            println!("{}", "─".repeat(80).bright_yellow());
+            println!("{}", "─".repeat(106).bright_yellow());
 
             println!(
-                "{:<15} {:>8} {:>10} {:>10} {:>12} {:>12}",
+                "{:<15} {:>8} {:>10} {:>10} {:>12} {:>6} {:>7} {:>10} {:>12}",
                 "Language".bold().bright_white(),
                 "Files".bold().bright_white(),
                 "Lines".bold().bright_white(),
                 "Code".bold().bright_white(),
                 "Comments".bold().bright_white(),
+                "Docs".bold().bright_white(),
+                "Mixed".bold().bright_white(),
+                "Generated".bold().bright_white(),
                 "Size".bold().bright_white()
             );
-            println!("{}", "─".repeat(80).bright_black());
+            println!("{}", "─".repeat(106).bright_black());
 
             // Sort by line count (descending)
             let mut sorted_types: Vec<_> = stats.file_types.iter().collect();
-            sorted_types.sort_by(|a, b| b.1.lines.cmp(&a.1.lines));
+            sorted_types.sort_by_key(|(_, file_stats)| std::cmp::Reverse(file_stats.lines));
 
             for (language, file_stats) in sorted_types {
                 let percentage = if stats.total_lines > 0 {
@@ -83,21 +131,207 @@ impl OutputFormatter {
                 };
 
                 println!(
-                    "{:<15} {:>8} {:>10} {:>10} {:>12} {:>12}",
+                    "{:<15} {:>8} {:>10} {:>10} {:>12} {:>6} {:>7} {:>10} {:>12}",
                     self.colorize_language(language),
                     file_stats.count.to_string().bright_white(),
                     format!("{} ({:.1}%)", file_stats.lines, percentage).green(),
                     file_stats.code_lines.to_string().blue(),
                     file_stats.comment_lines.to_string().yellow(),
+                    file_stats.doc_comment_lines.to_string().bright_yellow(),
+                    file_stats.mixed_lines.to_string().cyan(),
+                    file_stats.generated_lines.to_string().bright_black(),
                     format_bytes(file_stats.size_bytes).magenta()
                 );
             }
         }
 
+        if !stats.files.is_empty() {
+            println!("\n{}", "📄 Files".bold().yellow());
+            println!("{}", "─".repeat(95).bright_yellow());
+
+            println!(
+                "{:>10} {:>10} {:>10} {:>8} {:>12}  {:<15} {}",
+                "Lines".bold().bright_white(),
+                "Code".bold().bright_white(),
+                "Comments".bold().bright_white(),
+                "Blank".bold().bright_white(),
+                "Size".bold().bright_white(),
+                "Language".bold().bright_white(),
+                "Path".bold().bright_white()
+            );
+            println!("{}", "─".repeat(95).bright_black());
+
+            // Already ordered by `--sort`
+            for file in &stats.files {
+                let marker = match (file.generated, file.vendored) {
+                    (true, true) => " (generated, vendored)",
+                    (true, false) => " (generated)",
+                    (false, true) => " (vendored)",
+                    (false, false) => "",
+                };
+                println!(
+                    "{:>10} {:>10} {:>10} {:>8} {:>12}  {:<15} {}{}",
+                    file.lines.to_string().green(),
+                    file.code_lines.to_string().blue(),
+                    file.comment_lines.to_string().yellow(),
+                    file.blank_lines.to_string().bright_black(),
+                    format_bytes(file.size_bytes).magenta(),
+                    self.colorize_language(&file.language),
+                    file.path,
+                    marker.bright_black()
+                );
+            }
+        }
+
+        if let Some(directories) = &stats.directories {
+            println!("\n{}", "📂 Directories".bold().yellow());
+            println!("{}", "─".repeat(95).bright_yellow());
+
+            println!(
+                "{:<32} {:>8} {:>10} {:>10}  {}",
+                "Directory".bold().bright_white(),
+                "Files".bold().bright_white(),
+                "Lines".bold().bright_white(),
+                "Code".bold().bright_white(),
+                "Languages".bold().bright_white()
+            );
+            println!("{}", "─".repeat(95).bright_black());
+
+            self.display_directory(".", directories, 0);
+        }
+
+        if stats.vendored.total_files > 0 {
+            let vendored = &stats.vendored;
+            println!(
+                "\n{}",
+                "📦 Vendored Code (not in the totals above)".bold().yellow()
+            );
+            println!(
+                "{}  {} files, {} lines, {} code, {}",
+                "Total:".bright_white(),
+                vendored.total_files.to_string().bright_white(),
+                vendored.total_lines.to_string().green(),
+                vendored.total_code_lines.to_string().blue(),
+                format_bytes(vendored.total_size_bytes).magenta()
+            );
+
+            let mut vendored_types: Vec<_> = vendored.file_types.iter().collect();
+            vendored_types.sort_by_key(|(_, file_stats)| std::cmp::Reverse(file_stats.lines));
+
+            for (language, file_stats) in vendored_types {
+                println!(
+                    "{:<15} {} files, {} lines",
+                    self.colorize_language(language),
+                    file_stats.count,
+                    file_stats.lines.to_string().green()
+                );
+            }
+        }
+
+        if stats.total_test_lines > 0 {
+            println!("\n{}", "🧪 Production vs Test Code".bold().yellow());
+            println!(
+                "{:<15} {:>12} {:>10} {:>8}",
+                "Language".bold().bright_white(),
+                "Production".bold().bright_white(),
+                "Test".bold().bright_white(),
+                "Ratio".bold().bright_white()
+            );
+
+            let mut split: Vec<_> = stats.file_types.iter().collect();
+            split.sort_by_key(|(_, file_stats)| std::cmp::Reverse(file_stats.lines));
+
+            for (language, file_stats) in split {
+                let production = file_stats.lines - file_stats.test_lines;
+                let ratio = if production > 0 {
+                    format!("{:.2}", file_stats.test_lines as f64 / production as f64)
+                } else {
+                    "-".to_string()
+                };
+                println!(
+                    "{:<15} {:>12} {:>10} {:>8}",
+                    self.colorize_language(language),
+                    production.to_string().blue(),
+                    file_stats.test_lines.to_string().bright_green(),
+                    ratio
+                );
+            }
+        }
+
+        if stats.total_embedded_lines > 0 {
+            println!("\n{}", "🧩 Embedded Languages".bold().yellow());
+
+            let mut embedded: Vec<_> = stats
+                .file_types
+                .iter()
+                .filter(|(_, file_stats)| file_stats.embedded_lines > 0)
+                .collect();
+            embedded.sort_by_key(|(_, file_stats)| std::cmp::Reverse(file_stats.embedded_lines));
+
+            for (language, file_stats) in embedded {
+                println!(
+                    "{:<15} {} of {} lines",
+                    self.colorize_language(language),
+                    file_stats.embedded_lines.to_string().bright_blue(),
+                    file_stats.lines
+                );
+            }
+        }
+
+        if stats.total_notebook_cells > 0 {
+            println!("\n{}", "📓 Notebooks".bold().yellow());
+
+            let mut notebooks: Vec<_> = stats
+                .file_types
+                .iter()
+                .filter(|(_, file_stats)| file_stats.notebook_cells > 0)
+                .collect();
+            notebooks.sort_by_key(|(language, _)| *language);
+
+            for (language, file_stats) in notebooks {
+                println!(
+                    "{:<15} {} notebooks, {} cells ({:.1} per notebook)",
+                    self.colorize_language(language),
+                    file_stats.count,
+                    file_stats.notebook_cells.to_string().bright_blue(),
+                    file_stats.notebook_cells as f64 / file_stats.count.max(1) as f64
+                );
+            }
+        }
+
+        if stats.total_transcoded_files > 0 {
+            println!("\n{}", "🔤 Encodings".bold().yellow());
+            println!(
+                "{}  {}",
+                "Transcoded Files:".bright_white(),
+                stats.total_transcoded_files.to_string().cyan().bold()
+            );
+
+            let mut transcoded: Vec<_> = stats
+                .file_types
+                .iter()
+                .filter(|(_, file_stats)| file_stats.transcoded_files > 0)
+                .collect();
+            transcoded.sort_by_key(|(language, _)| *language);
+
+            for (language, file_stats) in transcoded {
+                let encodings: Vec<String> = file_stats
+                    .encodings
+                    .iter()
+                    .map(|(encoding, count)| format!("{} ×{}", encoding, count))
+                    .collect();
+                println!(
+                    "{:<15} {}",
+                    self.colorize_language(language),
+                    encodings.join(", ")
+                );
+            }
+        }
+
         if stats.file_types.len() > 3 {
             println!("\n{}", "🏆 Top Languages by Lines".bold().yellow());
             let mut top_types: Vec<_> = stats.file_types.iter().collect();
-            top_types.sort_by(|a, b| b.1.lines.cmp(&a.1.lines));
+            top_types.sort_by_key(|(_, file_stats)| std::cmp::Reverse(file_stats.lines));
 
             for (i, (language, file_stats)) in top_types.iter().take(5).enumerate() {
                 let medal = match i {
@@ -123,41 +357,124 @@ impl OutputFormatter {
         Ok(())
     }
 
+    /// One tree row per directory, children indented below their parent in name order.
+    fn display_directory(&self, name: &str, directory: &DirectoryStats, level: usize) {
+        let totals = directory.totals();
+
+        let mut languages: Vec<_> = directory
+            .file_types
+            .iter()
+            .filter(|(_, file_stats)| file_stats.lines > 0)
+            .collect();
+        languages.sort_by_key(|(_, file_stats)| std::cmp::Reverse(file_stats.lines));
+        let languages: Vec<String> = languages
+            .iter()
+            .take(3)
+            .map(|(language, file_stats)| format!("{} {}", language, file_stats.lines))
+            .collect();
+
+        let label = format!("{}{}", "  ".repeat(level), name);
+        println!(
+            "{:<32} {:>8} {:>10} {:>10}  {}",
+            label.bold(),
+            totals.count.to_string().bright_white(),
+            totals.lines.to_string().green(),
+            totals.code_lines.to_string().blue(),
+            languages.join(", ")
+        );
+
+        for (child_name, child) in &directory.directories {
+            self.display_directory(&format!("{}/", child_name), child, level + 1);
+        }
+    }
+
     fn display_json(&self, stats: &ProjectStats) -> Result<(), Box<dyn std::error::Error>> {
         let json = serde_json::to_string_pretty(stats)?;
         println!("{}", json);
         Ok(())
     }
 
+    /// One row per language with a TOTAL row, or one row per file with `--by-file`.
     fn display_csv(&self, stats: &ProjectStats) -> Result<(), Box<dyn std::error::Error>> {
-        println!("language,extension,files,lines,code_lines,comment_lines,blank_lines,size_bytes");
+        if !stats.files.is_empty() {
+            return self.display_file_csv(stats);
+        }
+
+        println!(
+            "language,extension,files,lines,code_lines,comment_lines,doc_comment_lines,blank_lines,mixed_lines,embedded_lines,notebook_cells,generated_files,generated_lines,production_lines,test_lines,transcoded_files,size_bytes"
+        );
 
         for (language, file_stats) in &stats.file_types {
             println!(
-                "{},multiple,{},{},{},{},{},{}",
+                "{},multiple,{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
                 language,
                 file_stats.count,
                 file_stats.lines,
                 file_stats.code_lines,
                 file_stats.comment_lines,
+                file_stats.doc_comment_lines,
                 file_stats.blank_lines,
+                file_stats.mixed_lines,
+                file_stats.embedded_lines,
+                file_stats.notebook_cells,
+                file_stats.generated_files,
+                file_stats.generated_lines,
+                file_stats.lines - file_stats.test_lines,
+                file_stats.test_lines,
+                file_stats.transcoded_files,
                 file_stats.size_bytes
             );
         }
 
         println!(
-            "TOTAL,ALL,{},{},{},{},{},{}",
+            "TOTAL,ALL,{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
             stats.total_files,
             stats.total_lines,
             stats.total_code_lines,
             stats.total_comment_lines,
+            stats.total_doc_comment_lines,
             stats.total_blank_lines,
+            stats.total_mixed_lines,
+            stats.total_embedded_lines,
+            stats.total_notebook_cells,
+            stats.total_generated_files,
+            stats.total_generated_lines,
+            stats.total_lines - stats.total_test_lines,
+            stats.total_test_lines,
+            stats.total_transcoded_files,
             stats.total_size_bytes
         );
 
         Ok(())
     }
 
+    fn display_file_csv(&self, stats: &ProjectStats) -> Result<(), Box<dyn std::error::Error>> {
+        println!(
+            "path,language,lines,code_lines,comment_lines,doc_comment_lines,blank_lines,mixed_lines,test_lines,notebook_cells,generated,vendored,size_bytes"
+        );
+
+        for file in &stats.files {
+            println!(
+                "{},{},{},{},{},{},{},{},{},{},{},{},{}",
+                csv_field(&file.path),
+                csv_field(&file.language),
+                file.lines,
+                file.code_lines,
+                file.comment_lines,
+                file.doc_comment_lines,
+                file.blank_lines,
+                file.mixed_lines,
+                file.test_lines,
+                file.notebook_cells,
+                file.generated,
+                file.vendored,
+                file.size_bytes
+            );
+        }
+
+        Ok(())
+    }
+
     fn colorize_language(&self, language: &str) -> ColoredString {
         match language {
             "Rust" => language.red().bold(),
@@ -203,6 +520,15 @@ fn format_bytes(bytes: u64) -> String {
     }
 }
 
+/// Quote a CSV field when it holds a separator, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -216,4 +542,11 @@ mod tests {
         assert_eq!(format_bytes(1048576), "1.0 MB");
         assert_eq!(format_bytes(1073741824), "1.0 GB");
     }
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("src/main.rs"), "src/main.rs");
+        assert_eq!(csv_field("a,b.rs"), "\"a,b.rs\"");
+        assert_eq!(csv_field("say \"hi\".txt"), "\"say \"\"hi\"\".txt\"");
+    }
 }